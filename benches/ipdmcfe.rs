@@ -18,7 +18,7 @@ fn bench_encrypt(c: &mut Criterion) {
         dpk.push(dpki);
     }
     // setup for client 0
-    let sk0 = setup(&dsk[0], &dpk, &mut rng);
+    let sk0 = setup(&dsk[0], &dpk, &mut rng).unwrap();
     // bench encryption for client 0
     c.bench_function("Encrypt one client:", |b| b.iter(|| encrypt(&x0, &sk0, &l)));
 }
@@ -49,15 +49,45 @@ fn bench_decrypt(c: &mut Criterion) {
     // in real life, propagate only `dpk` among clients
     let mut ctx = Vec::with_capacity(n_clients);
     for client in 0..n_clients {
-        let ski = setup(&dsk[client], &dpk, &mut rng);
+        let ski = setup(&dsk[client], &dpk, &mut rng).unwrap();
         pdk.push(dkey_gen_share(client, &ski, &y));
         ctx.push(encrypt(&x[client], &ski, &l));
     }
-    let dk = key_comb(&y, &pdk);
+    let dk = key_comb(&y, &pdk).unwrap();
 
     // bench encryption for client 0
     c.bench_function("Decrypt 10 clients:", |b| b.iter(|| decrypt(&ctx, &dk, &l)));
 }
 
-criterion_group!(benches, bench_encrypt, bench_decrypt);
+fn bench_dkey_gen_share(c: &mut Criterion) {
+    let mut rng = ThreadRng::default();
+    let n_clients = 10;
+    let y = (0..n_clients)
+        .map(|_| {
+            let mut bytes = [0; 64];
+            rng.fill_bytes(&mut bytes);
+            Scalar::from_bytes_wide(&bytes)
+        })
+        .collect::<Vec<_>>();
+    // create clients dsum keys
+    let (mut dsk, mut dpk) = (Vec::with_capacity(n_clients), Vec::with_capacity(n_clients));
+    for _ in 0..n_clients {
+        let dsum::KeyPair(dski, dpki) = dsum::client_setup(&mut rng);
+        dsk.push(dski);
+        dpk.push(dpki);
+    }
+    // setup for client 0
+    let sk0 = setup(&dsk[0], &dpk, &mut rng).unwrap();
+    let base0 = sk0.precompute_dkey_base();
+    // bench partial decryption key generation for client 0
+    c.bench_function("Partial decryption key one client:", |b| {
+        b.iter(|| dkey_gen_share(0, &sk0, &y))
+    });
+    c.bench_function(
+        "Partial decryption key one client, precomputed base:",
+        |b| b.iter(|| dkey_gen_share_fast(0, &base0, &sk0, &y)),
+    );
+}
+
+criterion_group!(benches, bench_encrypt, bench_decrypt, bench_dkey_gen_share);
 criterion_main!(benches);
@@ -0,0 +1,52 @@
+use cosmian_bls12_381::{G1Projective, G2Projective, Scalar};
+use criterion::{criterion_group, criterion_main, Criterion};
+use dmcfe::tools;
+use rand::{rngs::ThreadRng, RngCore};
+
+fn random_scalars(n: usize) -> Vec<Scalar> {
+    let mut rng = ThreadRng::default();
+    (0..n)
+        .map(|_| {
+            let mut bytes = [0; 64];
+            rng.fill_bytes(&mut bytes);
+            Scalar::from_bytes_wide(&bytes)
+        })
+        .collect()
+}
+
+fn bench_gen_mul_g1(c: &mut Criterion) {
+    let a = random_scalars(100);
+    // compute the table before benching
+    tools::gen_mul_g1(&a[0]);
+    c.bench_function("G1 generator multiplication, 100 scalars:", |b| {
+        b.iter(|| {
+            a.iter()
+                .map(|ai| G1Projective::generator() * ai)
+                .collect::<Vec<_>>()
+        })
+    });
+    c.bench_function(
+        "G1 generator multiplication with table, 100 scalars:",
+        |b| b.iter(|| a.iter().map(tools::gen_mul_g1).collect::<Vec<_>>()),
+    );
+}
+
+fn bench_gen_mul_g2(c: &mut Criterion) {
+    let a = random_scalars(100);
+    // compute the table before benching
+    tools::gen_mul_g2(&a[0]);
+    c.bench_function("G2 generator multiplication, 100 scalars:", |b| {
+        b.iter(|| {
+            a.iter()
+                .map(|ai| G2Projective::generator() * ai)
+                .collect::<Vec<_>>()
+        })
+    });
+    c.bench_function(
+        "G2 generator multiplication with table, 100 scalars:",
+        |b| b.iter(|| a.iter().map(tools::gen_mul_g2).collect::<Vec<_>>()),
+    );
+}
+
+criterion_group!(benches, bench_gen_mul_g1, bench_gen_mul_g2);
+criterion_main!(benches);
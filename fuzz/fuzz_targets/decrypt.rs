@@ -0,0 +1,29 @@
+#![no_main]
+
+use arbitrary::Unstructured;
+use dmcfe::{ipdmcfe, types::Label};
+use libfuzzer_sys::fuzz_target;
+
+// Combine arbitrary partial decryption keys and decrypt arbitrary cyphertexts:
+// the result is meaningless but the pipeline should never panic.
+fuzz_target!(|data: &[u8]| {
+    let mut u = Unstructured::new(data);
+    let (c, pdk, label): (
+        Vec<ipdmcfe::CypherText>,
+        Vec<ipdmcfe::PartialDecryptionKey>,
+        Vec<u8>,
+    ) = match (u.arbitrary(), u.arbitrary(), u.arbitrary()) {
+        (Ok(c), Ok(pdk), Ok(label)) => (c, pdk, label),
+        _ => return,
+    };
+    let dk: ipdmcfe::DecryptionKey = match u.arbitrary() {
+        Ok(dk) => dk,
+        Err(_) => return,
+    };
+    let mut l = Label::from("fuzz");
+    l.aggregate(label);
+    let _ = ipdmcfe::decrypt(&c, &dk, &l);
+    if let Ok(dk) = ipdmcfe::key_comb(&dk.y, &pdk) {
+        let _ = ipdmcfe::decrypt(&c, &dk, &l);
+    }
+});
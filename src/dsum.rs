@@ -1,10 +1,12 @@
 use crate::{tools, types::Label};
 use cosmian_bls12_381::{G1Projective, G2Projective, Scalar};
+use eyre::Result;
 use rand_core::{CryptoRng, RngCore};
+use std::collections::BTreeMap;
 use std::ops::{Deref, Mul};
 
 #[derive(Clone, Copy)]
-pub struct CypherText(Scalar);
+pub struct CypherText(pub(crate) Scalar);
 
 impl Deref for CypherText {
     type Target = Scalar;
@@ -22,6 +24,41 @@ impl Mul<&G2Projective> for &CypherText {
     }
 }
 
+impl Mul<&G1Projective> for &CypherText {
+    type Output = G1Projective;
+
+    fn mul(self, rhs: &G1Projective) -> Self::Output {
+        rhs * self.0
+    }
+}
+
+impl CypherText {
+    /// Size of a serialized cyphertext: format header and scalar
+    pub const SERIALIZED_LEN: usize = tools::codec::HEADER_LEN + 32;
+
+    /// Serialize the cyphertext as the format header followed by a 32-byte
+    /// scalar.
+    pub fn to_bytes(&self) -> [u8; Self::SERIALIZED_LEN] {
+        let mut res = [0; Self::SERIALIZED_LEN];
+        res[0] = tools::codec::FORMAT_VERSION;
+        res[1..].copy_from_slice(&self.0.to_bytes());
+        res
+    }
+
+    /// Deserialize a cyphertext serialized using `to_bytes`.
+    /// - `bytes`   : serialized cyphertext
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        tools::codec::read_header(&mut bytes)?;
+        let res = tools::read_scalar(&mut bytes)?;
+        eyre::ensure!(
+            bytes.is_empty(),
+            "{} unexpected trailing bytes in the serialized cyphertext!",
+            bytes.len()
+        );
+        Ok(Self(res))
+    }
+}
+
 impl Default for CypherText {
     fn default() -> Self {
         Self(Scalar::zero())
@@ -53,10 +90,16 @@ impl Deref for PublicKey {
 #[derive(Clone, Copy)]
 pub struct KeyPair(pub PrivateKey, pub PublicKey);
 
-/// Creates the private and public keys for a DSum client.
+/// Creates the private and public keys for a DSum client. A zero private key
+/// would give the identity as public key and break the masking: it is
+/// rejected and drawn again.
 pub fn client_setup<R: RngCore + CryptoRng>(rng: &mut R) -> KeyPair {
-    let t = tools::random_scalar(rng);
-    KeyPair(PrivateKey(t), PublicKey(tools::smul_in_g1(&t)))
+    loop {
+        let t = tools::random_scalar(rng);
+        if t != Scalar::zero() {
+            return KeyPair(PrivateKey(t), PublicKey(tools::smul_in_g1(&t)));
+        }
+    }
 }
 
 /// Encrypt the given data using the given keys and label.
@@ -72,8 +115,51 @@ pub fn encode(x: &Scalar, ski: &PrivateKey, pk_list: &[PublicKey], label: &Label
     )
 }
 
+/// Return the additive share of zero of a client for the given label: the
+/// shares of all clients sum to zero. This is the mask added by `encode`,
+/// i.e. `encode(x, ski, pk_list, label) = x + share_of_zero(ski, pk_list,
+/// label)`, and can be consumed by external additive secret sharing systems.
+/// - `ski`:    client private key
+/// - `pk`:     list of all public keys
+/// - `l`:      label
+pub fn share_of_zero(ski: &PrivateKey, pk_list: &[PublicKey], label: &Label) -> Scalar {
+    encode(&Scalar::zero(), ski, pk_list, label).0
+}
+
 /// Decrypt the given data.
 /// - `c`:  list of all encrypted data
 pub fn combine(c: &[CypherText]) -> Scalar {
     c.iter().map(|&CypherText(ci)| ci).sum()
 }
+
+/// Decrypt the data of several rounds, given as `(cyphertext, client ID)`
+/// pairs in any order. The masks only cancel out when all the clients of the
+/// key set contributed: fail unless each round holds exactly one cyphertext
+/// from each of the clients `0..n`, instead of returning wrong results.
+/// Return one result per round.
+/// - `rounds`: encrypted data of each round, along with the client IDs
+/// - `n`:      number of clients in the key set
+pub fn combine_aligned(rounds: &[Vec<(CypherText, usize)>], n: usize) -> Result<Vec<Scalar>> {
+    rounds
+        .iter()
+        .enumerate()
+        .map(|(k, round)| {
+            let aligned = round
+                .iter()
+                .map(|&(ci, id)| (id, ci))
+                .collect::<BTreeMap<usize, CypherText>>();
+            eyre::ensure!(
+                aligned.len() == round.len(),
+                "Duplicate client IDs in round {}!",
+                k
+            );
+            eyre::ensure!(
+                aligned.keys().copied().eq(0..n),
+                "Round {} does not hold the cyphertexts of the {} clients!",
+                k,
+                n
+            );
+            Ok(aligned.values().map(|&CypherText(ci)| ci).sum())
+        })
+        .collect()
+}
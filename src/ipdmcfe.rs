@@ -1,14 +1,104 @@
 use crate::{
     dsum, tools,
-    types::{DVec, Label, TMat},
+    types::{BoundedScalarSum, ClientIndexed, DVec, Label, TMat},
 };
 use cosmian_bls12_381::{pairing, G1Affine, G1Projective, G2Affine, G2Projective, Gt, Scalar};
+use eyre::Result;
 use rand_core::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::ops::{Add, AddAssign, Deref};
+use std::sync::{Mutex, OnceLock};
+
+/// Default maximum number of clients accepted by `setup` and `key_comb`
+pub const MAX_CLIENTS: usize = 1 << 12;
 
 /// DMCFE cyphertext type
 #[derive(Clone, Copy)]
 pub struct CypherText(G1Projective);
 
+impl Deref for CypherText {
+    type Target = G1Projective;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl CypherText {
+    /// Size of a serialized cyphertext: format header and compressed G1 point
+    pub const SERIALIZED_LEN: usize = tools::codec::HEADER_LEN + 48;
+
+    /// Rerandomize the cyphertext by adding a random multiple `r.g1` of the G1
+    /// generator, so that it cannot be linked to the original cyphertext.
+    /// Return the new cyphertext along with the offset `r`.
+    ///
+    /// Decrypting rerandomized cyphertexts shifts the result by
+    /// `Sum(yi.ri).gT`, where `ri` is the offset of the cyphertext `i` (zero
+    /// if it was not rerandomized). The offsets must therefore be transmitted
+    /// to the decryptor, who removes this shift using `remove_offsets`.
+    /// - `rng` : random number generator
+    pub fn rerandomize<R: CryptoRng + RngCore>(&self, rng: &mut R) -> (Self, Scalar) {
+        let r = tools::random_scalar(rng);
+        (Self(self.0 + tools::smul_in_g1(&r)), r)
+    }
+
+    /// Serialize the cyphertext as the format header followed by a compressed
+    /// G1 point.
+    pub fn to_bytes(&self) -> [u8; Self::SERIALIZED_LEN] {
+        let mut res = [0; Self::SERIALIZED_LEN];
+        res[0] = tools::codec::FORMAT_VERSION;
+        res[1..].copy_from_slice(&G1Affine::from(self.0).to_compressed());
+        res
+    }
+
+    /// Deserialize a cyphertext serialized using `to_bytes`. Fail if the
+    /// encoding is invalid or if the point is not in the prime-order
+    /// subgroup, which prevents small-subgroup attacks from untrusted peers.
+    /// The subgroup check costs about one scalar multiplication.
+    /// - `bytes`   : serialized cyphertext
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::decode(bytes, true)
+    }
+
+    /// Deserialize a cyphertext serialized using `to_bytes`, without checking
+    /// that the point is in the prime-order subgroup. Only use it for trusted
+    /// inputs.
+    /// - `bytes`   : serialized cyphertext
+    pub fn from_bytes_unchecked(bytes: &[u8]) -> Result<Self> {
+        Self::decode(bytes, false)
+    }
+
+    fn decode(mut bytes: &[u8], checked: bool) -> Result<Self> {
+        tools::codec::read_header(&mut bytes)?;
+        let bytes = <&[u8; 48]>::try_from(bytes).map_err(|_| {
+            eyre::eyre!(
+                "Wrong cyphertext size: {} bytes instead of 48!",
+                bytes.len()
+            )
+        })?;
+        tools::g1_from_compressed(bytes, checked)
+            .map(Self)
+            .ok_or_else(|| eyre::eyre!("Invalid cyphertext encoding!"))
+    }
+}
+
+/// Return the size of `n` serialized cyphertexts, e.g. to preallocate the
+/// buffer receiving the cyphertexts of `dk.client_count()` clients.
+/// - `n`   : number of cyphertexts
+pub fn expected_ciphertext_bytes(n: usize) -> usize {
+    n * CypherText::SERIALIZED_LEN
+}
+
+/// Fuzzing only: build a valid cyphertext from fuzzer bytes.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for CypherText {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self(tools::smul_in_g1(&tools::arbitrary_scalar(u)?)))
+    }
+}
+
 /// DMCFE private key type
 #[derive(Clone)]
 pub struct PrivateKey {
@@ -16,12 +106,140 @@ pub struct PrivateKey {
     pub s: DVec<Scalar>,
     /// - `t`:  2x2 scalar matrix
     pub t: TMat<dsum::CypherText>,
+    /// - `used_labels`: labels already used by `encrypt_once`
+    used_labels: HashSet<Vec<u8>>,
+}
+
+impl PrivateKey {
+    /// PEM label of the serialized private keys
+    const PEM_LABEL: &'static str = "DMCFE PRIVATE KEY";
+
+    /// Serialize the private key as the format header followed by the
+    /// 32-byte scalars of `s` and of `t`, in row-major order. The labels used
+    /// by `encrypt_once` are not serialized.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut res = tools::codec::new_buffer(6 * 32);
+        self.s
+            .iter()
+            .for_each(|si| res.extend_from_slice(&si.to_bytes()));
+        self.t
+            .iter()
+            .flatten()
+            .for_each(|tij| res.extend_from_slice(&tij.0.to_bytes()));
+        res
+    }
+
+    /// Deserialize a private key serialized using `to_bytes`.
+    /// - `bytes`   : serialized private key
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        let bytes = &mut bytes;
+        tools::codec::read_header(bytes)?;
+        let s = DVec::new(tools::read_scalar(bytes)?, tools::read_scalar(bytes)?);
+        let t = (0..4)
+            .map(|_| tools::read_scalar(bytes).map(dsum::CypherText))
+            .collect::<Result<Vec<_>>>()?;
+        eyre::ensure!(
+            bytes.is_empty(),
+            "{} unexpected trailing bytes in the serialized private key!",
+            bytes.len()
+        );
+        Ok(Self {
+            s,
+            t: TMat::new(t[0], t[1], t[2], t[3]),
+            used_labels: HashSet::new(),
+        })
+    }
+
+    /// Serialize the private key as a PEM block labelled `DMCFE PRIVATE KEY`,
+    /// holding the base64 encoding of `to_bytes`, for key management systems
+    /// storing keys as PEM.
+    pub fn to_pem(&self) -> String {
+        tools::codec::to_pem(Self::PEM_LABEL, &self.to_bytes())
+    }
+
+    /// Deserialize a private key serialized using `to_pem`. Fail if the PEM
+    /// label is not `DMCFE PRIVATE KEY`.
+    /// - `pem` : PEM block
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        Self::from_bytes(&tools::codec::from_pem(Self::PEM_LABEL, pem)?)
+    }
+
+    /// Precompute the G2 basis `s.g2` used by `dkey_gen_share_fast`. Since `s`
+    /// is fixed, a client serving many decryption functions only computes it
+    /// once.
+    pub fn precompute_dkey_base(&self) -> DkeyBase {
+        DkeyBase(&self.s * &G2Projective::generator())
+    }
+
+    /// Encrypts the data of the client for a given label, and record the
+    /// label in the key. Fail if the label was already used through this
+    /// function: encrypting two values under the same label leaks their
+    /// difference. Clones of the key do not share their used labels.
+    /// - `xi`  : contribution
+    /// - `l`   : label
+    pub fn encrypt_once(&mut self, xi: &Scalar, l: &Label) -> Result<CypherText> {
+        eyre::ensure!(
+            self.used_labels.insert(l.canonical_bytes().to_vec()),
+            "This label was already used to encrypt with this key!"
+        );
+        Ok(encrypt(xi, self, l))
+    }
+}
+
+/// Precomputed G2 basis `s.g2` of a DMCFE client
+#[derive(Clone)]
+pub struct DkeyBase(DVec<G2Projective>);
+
+/// Auxiliary data of a DMCFE encryption: `c = mask + xi.g1`. Since the
+/// encryption is deterministic given the key and the label, no randomness is
+/// consumed: a prover can reference these terms to build a proof about the
+/// cyphertext (e.g. a NIZK of the knowledge of `xi`).
+#[derive(Clone)]
+pub struct EncryptionAux {
+    /// - `u`   : cyphertext basis `H(l)`
+    pub u: DVec<G1Projective>,
+    /// - `mask`: masking term `<u, s>`
+    pub mask: G1Projective,
 }
 
 /// DMCFE partial decryption key type: `di`
 #[derive(Clone)]
 pub struct PartialDecryptionKey(DVec<G2Projective>);
 
+impl PartialDecryptionKey {
+    /// Return the zero partial decryption key. This is the identity of the
+    /// combination of partial decryption keys: it can be used as the starting
+    /// point of a fold.
+    pub fn zero() -> Self {
+        Self(DVec::default())
+    }
+}
+
+impl Default for PartialDecryptionKey {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl<'a> Add<&'a Self> for PartialDecryptionKey {
+    type Output = Self;
+
+    fn add(self, rhs: &'a Self) -> Self::Output {
+        Self(self.0 + &rhs.0)
+    }
+}
+
+/// Fuzzing only: build a valid partial decryption key from fuzzer bytes.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for PartialDecryptionKey {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self(DVec::new(
+            tools::smul_in_g2(&tools::arbitrary_scalar(u)?),
+            tools::smul_in_g2(&tools::arbitrary_scalar(u)?),
+        )))
+    }
+}
+
 /// DMCFE decryption key type: `(y, d)`
 #[derive(Clone)]
 pub struct DecryptionKey {
@@ -31,6 +249,75 @@ pub struct DecryptionKey {
     d: DVec<G2Projective>,
 }
 
+impl DecryptionKey {
+    /// Combine a late partial decryption key into the decryption key, instead
+    /// of combining all the partial decryption keys again.
+    ///
+    /// Only use it for a partial decryption key which is genuinely missing
+    /// from the key: adding the same partial decryption key twice silently
+    /// breaks decryption.
+    ///
+    /// - `pdki`: partial decryption key
+    pub fn add_partial(&mut self, pdki: &PartialDecryptionKey) {
+        self.d += &pdki.0;
+    }
+
+    /// Return the number of clients of the decryption function.
+    pub fn client_count(&self) -> usize {
+        self.y.len()
+    }
+}
+
+/// Fuzzing only: build a valid decryption key from fuzzer bytes.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for DecryptionKey {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let n = u.arbitrary_len::<[u8; 64]>()?;
+        let y = (0..n)
+            .map(|_| tools::arbitrary_scalar(u))
+            .collect::<arbitrary::Result<Vec<_>>>()?;
+        let PartialDecryptionKey(d) = u.arbitrary()?;
+        Ok(Self { y, d })
+    }
+}
+
+/// DMCFE decryption key builder: combine the partial decryption keys one at a
+/// time, starting from the zero key.
+#[derive(Clone)]
+pub struct DecryptionKeyBuilder {
+    /// - `y`:  decryption function
+    y: Vec<Scalar>,
+    /// - `d`:  combination of the partial decryption keys received so far
+    d: PartialDecryptionKey,
+}
+
+impl DecryptionKeyBuilder {
+    /// Create a new builder for the given decryption function.
+    /// - `y`   : decryption function
+    pub fn new(y: &[Scalar]) -> Self {
+        Self {
+            y: y.to_vec(),
+            d: PartialDecryptionKey::zero(),
+        }
+    }
+
+    /// Combine the given partial decryption key.
+    /// - `pdki`: partial decryption key
+    pub fn add_partial_key(mut self, pdki: &PartialDecryptionKey) -> Self {
+        self.d = self.d + pdki;
+        self
+    }
+
+    /// Return the decryption key. The partial decryption keys of all clients
+    /// should have been combined.
+    pub fn build(self) -> DecryptionKey {
+        DecryptionKey {
+            y: self.y,
+            d: self.d.0,
+        }
+    }
+}
+
 /// Create `Ti`, such that `Sum(Ti) = 0`.
 /// - `dski`: DSum secret key
 /// - `dpk` : DSum public keys from all clients
@@ -44,7 +331,227 @@ fn t_gen(dski: &dsum::PrivateKey, dpk: &[dsum::PublicKey]) -> TMat<dsum::CypherT
     TMat::new(res[0], res[1], res[2], res[3])
 }
 
-/// Return the DMCFE secret key.
+/// Compute the cyphertext basis `u = H(l)` associated to the given label.
+/// Both `encrypt` and `decrypt` use this function to ensure they agree on it.
+/// - `l`   : label
+fn label_basis(l: &Label) -> DVec<G1Projective> {
+    DVec::from(tools::double_hash_to_curve_in_g1(l.canonical_bytes()))
+}
+
+/// Fold the associated data into the label: `l | aad | len(aad)`. The length
+/// suffix ensures two different `(l, aad)` pairs give different labels.
+/// - `l`   : label
+/// - `aad` : associated data
+fn aad_label(l: &Label, aad: &[u8]) -> Label {
+    let mut res = l.clone();
+    res.aggregate(aad);
+    res.aggregate((aad.len() as u64).to_le_bytes());
+    res
+}
+
+/// DMCFE configuration:
+/// - `max_clients`: maximum number of clients accepted by `setup` and `key_comb`
+#[derive(Clone, Copy)]
+pub struct Config {
+    max_clients: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_clients: MAX_CLIENTS,
+        }
+    }
+}
+
+impl Config {
+    /// Override the maximum number of clients, for large deployments.
+    /// - `max_clients`: maximum number of clients
+    pub fn max_clients(mut self, max_clients: usize) -> Self {
+        self.max_clients = max_clients;
+        self
+    }
+
+    /// Check the given number of clients does not exceed the configured cap.
+    /// - `n`   : number of clients
+    fn check_client_count(&self, n: usize) -> Result<()> {
+        eyre::ensure!(
+            n <= self.max_clients,
+            "Too many clients: {} instead of {} at most!",
+            n,
+            self.max_clients
+        );
+        Ok(())
+    }
+
+    /// Return the DMCFE secret key. Fail if two DSum public keys are equal or
+    /// if a DSum public key is the identity, which reveals an RNG failure or
+    /// an attack.
+    /// - `dski`: DSum secret key
+    /// - `dpk` : DSum public keys from all clients
+    /// - `rng` : random number generator
+    pub fn setup<R: CryptoRng + RngCore>(
+        &self,
+        dski: &dsum::PrivateKey,
+        dpk: &[dsum::PublicKey],
+        rng: &mut R,
+    ) -> Result<PrivateKey> {
+        self.check_client_count(dpk.len())?;
+        let mut keys = HashSet::with_capacity(dpk.len());
+        for (i, dpki) in dpk.iter().enumerate() {
+            eyre::ensure!(
+                !bool::from(dpki.is_identity()),
+                "WeakKey: the DSum public key {} is the identity!",
+                i
+            );
+            eyre::ensure!(
+                keys.insert(G1Affine::from(**dpki).to_compressed()),
+                "DuplicateKey: the DSum public key {} was already given!",
+                i
+            );
+        }
+        Ok(PrivateKey {
+            s: DVec::new(tools::random_scalar(rng), tools::random_scalar(rng)),
+            t: t_gen(dski, dpk),
+            used_labels: HashSet::new(),
+        })
+    }
+
+    /// Check the sizes of a decryption function and of the partial decryption
+    /// keys to combine, then sum these keys. Both variants of the scheme
+    /// combine their keys through this function. The sum does not depend on
+    /// the order of the partial decryption keys.
+    /// - `y`   : decryption function
+    /// - `pdk` : partial decryption keys
+    fn combine_partial_keys<'k, T>(
+        &self,
+        y: &[Scalar],
+        pdk: impl ExactSizeIterator<Item = &'k DVec<T>>,
+    ) -> Result<DVec<T>>
+    where
+        T: 'k + AddAssign<&'k T> + Clone + Default,
+    {
+        self.check_client_count(y.len())?;
+        self.check_client_count(pdk.len())?;
+        eyre::ensure!(pdk.len() != 0, "No partial decryption key to combine!");
+        Ok(pdk.fold(DVec::default(), |acc, di| acc + di))
+    }
+
+    /// Combine the partial decryption keys to return the final decryption key.
+    /// Fail if no partial decryption key is given.
+    /// - `y`   : decryption function
+    /// - `pdk` : partial decryption keys
+    pub fn key_comb(&self, y: &[Scalar], pdk: &[PartialDecryptionKey]) -> Result<DecryptionKey> {
+        Ok(DecryptionKey {
+            y: y.to_vec(),
+            d: self.combine_partial_keys(y, pdk.iter().map(|PartialDecryptionKey(di)| di))?,
+        })
+    }
+
+    /// Combine the stored partial decryption keys to return the final
+    /// decryption key. Fail if the store does not hold exactly the keys of the
+    /// clients `0..y.len()`.
+    /// - `y`       : decryption function
+    /// - `store`   : stored partial decryption keys
+    pub fn key_comb_from_store(
+        &self,
+        y: &[Scalar],
+        store: &PartialKeyStore,
+    ) -> Result<DecryptionKey> {
+        eyre::ensure!(
+            store.0.len() == y.len() && store.0.keys().copied().eq(0..y.len()),
+            "Incomplete store: {} partial decryption keys stored for {} clients!",
+            store.0.len(),
+            y.len()
+        );
+        self.key_comb(y, &store.0.values().cloned().collect::<Vec<_>>())
+    }
+
+    /// Combine the partial decryption keys to return the final decryption key,
+    /// in the G2 variant. Fail if no partial decryption key is given.
+    /// - `y`   : decryption function
+    /// - `pdk` : partial decryption keys
+    pub fn key_comb_g1(
+        &self,
+        y: &[Scalar],
+        pdk: &[PartialDecryptionKeyG1],
+    ) -> Result<DecryptionKeyG1> {
+        Ok(DecryptionKeyG1 {
+            y: y.to_vec(),
+            d: self.combine_partial_keys(y, pdk.iter().map(|PartialDecryptionKeyG1(di)| di))?,
+        })
+    }
+
+    /// Check the consistency of a freshly distributed set of DMCFE keys. This
+    /// is an in-process diagnostic: it requires the private keys of all
+    /// clients and should never be run by an untrusted party.
+    /// - `keys`: private keys of all the clients, ordered by client ID
+    pub fn healthcheck(&self, keys: &[PrivateKey]) -> Result<HealthReport> {
+        eyre::ensure!(!keys.is_empty(), "No key to check!");
+
+        let t_sum = (0..2).all(|i| {
+            (0..2).all(|j| keys.iter().map(|ski| *ski.t[i][j]).sum::<Scalar>() == Scalar::zero())
+        });
+
+        // a zero component of `s` removes the corresponding part of the mask
+        let s_nonzero = keys
+            .iter()
+            .all(|ski| ski.s.iter().all(|si| *si != Scalar::zero()));
+
+        // deterministic contributions and decryption function
+        let x = (1..=keys.len() as u64)
+            .map(Scalar::from)
+            .collect::<Vec<_>>();
+        let y = x.clone();
+        let l = Label::from("Healthcheck");
+        let c = x
+            .iter()
+            .zip(keys.iter())
+            .map(|(xi, ski)| encrypt(xi, ski, &l))
+            .collect::<Vec<_>>();
+        let pdk = keys
+            .iter()
+            .enumerate()
+            .map(|(id, ski)| dkey_gen_share(id, ski, &y))
+            .collect::<Vec<_>>();
+        let expected = Gt::generator()
+            * x.iter()
+                .zip(y.iter())
+                .map(|(xi, yi)| xi * yi)
+                .sum::<Scalar>();
+        let round_trip = decrypt(&c, &self.key_comb(&y, &pdk)?, &l)? == expected;
+
+        Ok(HealthReport {
+            t_sum,
+            s_nonzero,
+            round_trip,
+        })
+    }
+
+    /// Return an empty decryptor session combining the partial decryption
+    /// keys with this configuration.
+    pub fn session(&self) -> DecryptorSession {
+        DecryptorSession {
+            config: *self,
+            ..DecryptorSession::default()
+        }
+    }
+
+    /// Store the partial decryption keys, which are combined with this
+    /// configuration on first use.
+    /// - `y`   : decryption function
+    /// - `pdk` : partial decryption keys
+    pub fn lazy_key(&self, y: &[Scalar], pdk: &[PartialDecryptionKey]) -> LazyDecryptionKey {
+        LazyDecryptionKey {
+            y: y.to_vec(),
+            pdk: pdk.to_vec(),
+            config: *self,
+            dk: OnceLock::new(),
+        }
+    }
+}
+
+/// Return the DMCFE secret key, using the default configuration.
 /// - `dski`: DSum secret key
 /// - `dpk` : DSum public keys from all clients
 /// - `rng` : random number generator
@@ -52,33 +559,273 @@ pub fn setup<R: CryptoRng + RngCore>(
     dski: &dsum::PrivateKey,
     dpk: &[dsum::PublicKey],
     rng: &mut R,
-) -> PrivateKey {
-    PrivateKey {
-        s: DVec::new(tools::random_scalar(rng), tools::random_scalar(rng)),
-        t: t_gen(dski, dpk),
-    }
+) -> Result<PrivateKey> {
+    Config::default().setup(dski, dpk, rng)
+}
+
+/// Build the decryption function computing the sum of the contributions of
+/// `n` clients: `y = (1, ..., 1)`.
+/// - `n`   : number of clients
+pub fn function_sum(n: usize) -> Vec<Scalar> {
+    vec![Scalar::one(); n]
+}
+
+/// Build the decryption function computing the mean of the contributions of
+/// `n` clients. Dividing in the scalar field would not give the integer mean,
+/// so this is the sum function: the integer recovered from the decryption
+/// result must then be divided by `n`.
+/// - `n`   : number of clients
+pub fn function_mean(n: usize) -> Vec<Scalar> {
+    function_sum(n)
 }
 
 /// Compute the DMCFE partial decryption key.
+///
+/// Every client must send its partial decryption key, even if its
+/// coefficient `y[id]` is zero: the key also carries the term `Ti.H(y)`,
+/// and the `Ti` only cancel out when summed over all the clients.
+///
 /// - `id`  : client ID
 /// - `ski` : private key
 /// - `y`   : decryption function
 pub fn dkey_gen_share(id: usize, ski: &PrivateKey, y: &[Scalar]) -> PartialDecryptionKey {
-    let v = DVec::from(tools::double_hash_to_curve_in_g2(&Label::from(y)));
-    PartialDecryptionKey(&(&ski.s * &y[id]) * &G2Projective::generator() + &(&ski.t * &v))
+    let v = DVec::from(tools::double_hash_to_curve_in_g2(
+        Label::from(y).canonical_bytes(),
+    ));
+    let mut sy = &ski.s * &y[id];
+    let mut syg = &sy * &G2Projective::generator();
+    let mut tv = &ski.t * &v;
+    let res = PartialDecryptionKey(syg.clone() + &tv);
+
+    // wipe the secret-derived terms
+    tools::wipe(&mut sy[..]);
+    tools::wipe(&mut syg[..]);
+    tools::wipe(&mut tv[..]);
+    res
+}
+
+/// Commitment to the decryption function served by a client: `H(id | y)`. It
+/// lets an auditor holding `y` later verify which functions a client
+/// participated in, while third parties only see a hash. Since it is
+/// deterministic, a function drawn from a small set can be recovered by
+/// exhaustive search.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FunctionCommitment([u8; 32]);
+
+impl Deref for FunctionCommitment {
+    type Target = [u8; 32];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl FunctionCommitment {
+    /// Commit to the decryption function served by the given client.
+    /// - `y`   : decryption function
+    /// - `id`  : client ID
+    pub fn new(y: &[Scalar], id: usize) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(b"FunctionCommitment");
+        hasher.update((id as u64).to_le_bytes());
+        hasher.update(Label::from(y).canonical_bytes());
+        let mut res = [0; 32];
+        res.copy_from_slice(&hasher.finalize());
+        Self(res)
+    }
+}
+
+/// Compute the DMCFE partial decryption key, along with a commitment to the
+/// decryption function that the client can keep as a record.
+/// - `id`  : client ID
+/// - `ski` : private key
+/// - `y`   : decryption function
+pub fn dkey_gen_share_with_commitment(
+    id: usize,
+    ski: &PrivateKey,
+    y: &[Scalar],
+) -> (PartialDecryptionKey, FunctionCommitment) {
+    (dkey_gen_share(id, ski, y), FunctionCommitment::new(y, id))
 }
 
-/// Combine the partial decryption keys to return the final decryption key.
+/// Return `true` if the given commitment was produced by the given client for
+/// the given decryption function.
+/// - `commitment`  : function commitment
+/// - `y`           : decryption function
+/// - `id`          : client ID
+pub fn verify_function_commitment(
+    commitment: &FunctionCommitment,
+    y: &[Scalar],
+    id: usize,
+) -> bool {
+    *commitment == FunctionCommitment::new(y, id)
+}
+
+/// Compute the DMCFE partial decryption key, using the precomputed basis of
+/// the client. Give the same result as `dkey_gen_share`.
+/// - `id`  : client ID
+/// - `base`: precomputed basis, given by `ski.precompute_dkey_base()`
+/// - `ski` : private key
+/// - `y`   : decryption function
+pub fn dkey_gen_share_fast(
+    id: usize,
+    base: &DkeyBase,
+    ski: &PrivateKey,
+    y: &[Scalar],
+) -> PartialDecryptionKey {
+    let v = DVec::from(tools::double_hash_to_curve_in_g2(
+        Label::from(y).canonical_bytes(),
+    ));
+    let mut syg = &base.0 * &y[id];
+    let mut tv = &ski.t * &v;
+    let res = PartialDecryptionKey(syg.clone() + &tv);
+
+    // wipe the secret-derived terms
+    tools::wipe(&mut syg[..]);
+    tools::wipe(&mut tv[..]);
+    res
+}
+
+/// Combine the partial decryption keys to return the final decryption key,
+/// using the default configuration. The partial decryption keys of all the
+/// clients are required, including the ones with a zero coefficient in `y`.
+/// They can be given in any order: the decryption key does not depend on
+/// it, which is a guaranteed invariant.
 /// - `y`   : decryption function
 /// - `pdk` : partial decryption keys
-pub fn key_comb(y: &[Scalar], pdk: &[PartialDecryptionKey]) -> DecryptionKey {
-    DecryptionKey {
-        y: y.to_vec(),
-        d: pdk
-            .iter()
-            .map(|PartialDecryptionKey(di)| di)
-            .fold(DVec::default(), |acc, e| acc + e),
+pub fn key_comb(y: &[Scalar], pdk: &[PartialDecryptionKey]) -> Result<DecryptionKey> {
+    Config::default().key_comb(y, pdk)
+}
+
+/// Collection of partial decryption keys indexed by client ID, which can be
+/// stored and combined much later (e.g. for audits).
+#[derive(Clone, Default)]
+pub struct PartialKeyStore(BTreeMap<usize, PartialDecryptionKey>);
+
+impl PartialKeyStore {
+    /// Store the partial decryption key of the client with the given ID. Fail
+    /// if a key was already stored for this client.
+    /// - `id`  : client ID
+    /// - `pdki`: partial decryption key
+    pub fn insert(&mut self, id: usize, pdki: PartialDecryptionKey) -> Result<()> {
+        eyre::ensure!(
+            !self.0.contains_key(&id),
+            "A partial decryption key was already stored for client {}!",
+            id
+        );
+        self.0.insert(id, pdki);
+        Ok(())
     }
+
+    /// Return the number of stored partial decryption keys.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Return `true` if no partial decryption key is stored.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Serialize the store. The number of keys and the client IDs are encoded
+    /// as little-endian `u64`, the keys as compressed G2 points:
+    /// `header | n | (id_i | d_i) for i in 0..n`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut res = tools::codec::new_buffer(8 + self.0.len() * (8 + 2 * 96));
+        res.extend_from_slice(&(self.0.len() as u64).to_le_bytes());
+        for (id, PartialDecryptionKey(di)) in &self.0 {
+            res.extend_from_slice(&(*id as u64).to_le_bytes());
+            di.iter()
+                .for_each(|dij| res.extend_from_slice(&G2Affine::from(dij).to_compressed()));
+        }
+        res
+    }
+
+    /// Deserialize a store serialized using `to_bytes`.
+    /// - `bytes`   : serialized store
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        let bytes = &mut bytes;
+        tools::codec::read_header(bytes)?;
+        let n = tools::read_len(bytes, 8 + 2 * 96)?;
+        let mut res = Self::default();
+        for _ in 0..n {
+            let id = usize::try_from(tools::read_u64(bytes)?)?;
+            let mut points = Vec::with_capacity(2);
+            for _ in 0..2 {
+                let (point, tail) = bytes.split_at(96);
+                *bytes = tail;
+                points.push(
+                    tools::g2_from_compressed(<&[u8; 96]>::try_from(point)?, true)
+                        .ok_or_else(|| eyre::eyre!("Invalid partial decryption key encoding!"))?,
+                );
+            }
+            res.insert(id, PartialDecryptionKey(DVec::new(points[0], points[1])))?;
+        }
+        eyre::ensure!(
+            bytes.is_empty(),
+            "{} unexpected trailing bytes in the serialized store!",
+            bytes.len()
+        );
+        Ok(res)
+    }
+}
+
+/// Combine the stored partial decryption keys to return the final decryption
+/// key, using the default configuration. Fail if the store does not hold
+/// exactly the keys of the clients `0..y.len()`.
+/// - `y`       : decryption function
+/// - `store`   : stored partial decryption keys
+pub fn key_comb_from_store(y: &[Scalar], store: &PartialKeyStore) -> Result<DecryptionKey> {
+    Config::default().key_comb_from_store(y, store)
+}
+
+/// Receive partial decryption keys from the given channel and combine them,
+/// until the keys of all the expected clients have been received. Using a
+/// bounded channel applies backpressure to the senders.
+///
+/// Fail if a key is received from an unexpected client or twice from the same
+/// client, if the channel is closed or if the timeout elapses before all keys
+/// are received.
+///
+/// - `rx`          : channel of partial decryption keys
+/// - `y`           : decryption function
+/// - `expected_ids`: IDs of the participating clients
+/// - `timeout`     : maximum time to wait for the keys
+#[cfg(feature = "async")]
+pub async fn collect_partial_keys(
+    rx: &mut tokio::sync::mpsc::Receiver<ClientIndexed<PartialDecryptionKey>>,
+    y: &[Scalar],
+    expected_ids: &HashSet<usize>,
+    timeout: std::time::Duration,
+) -> Result<DecryptionKey> {
+    let collect = async {
+        let mut builder = DecryptionKeyBuilder::new(y);
+        let mut received = HashSet::with_capacity(expected_ids.len());
+        while received.len() < expected_ids.len() {
+            let pdki = rx.recv().await.ok_or_else(|| {
+                eyre::eyre!(
+                    "Channel closed after receiving {} partial decryption keys out of {}!",
+                    received.len(),
+                    expected_ids.len()
+                )
+            })?;
+            eyre::ensure!(
+                expected_ids.contains(&pdki.id()),
+                "Unexpected client ID {}!",
+                pdki.id()
+            );
+            eyre::ensure!(
+                received.insert(pdki.id()),
+                "Partial decryption key received twice from client {}!",
+                pdki.id()
+            );
+            builder = builder.add_partial_key(pdki.value());
+        }
+        Ok(builder.build())
+    };
+    tokio::time::timeout(timeout, collect)
+        .await
+        .map_err(|_| eyre::eyre!("Timeout while waiting for the partial decryption keys!"))?
 }
 
 /// Encrypts the data of a client `i` for a given label and encryption key.
@@ -86,25 +833,972 @@ pub fn key_comb(y: &[Scalar], pdk: &[PartialDecryptionKey]) -> DecryptionKey {
 /// - `ski` : encryption key
 /// - `l`   : label
 pub fn encrypt(xi: &Scalar, ski: &PrivateKey, l: &Label) -> CypherText {
-    let u = DVec::from(tools::double_hash_to_curve_in_g1(l));
-    CypherText(u.inner_product(&ski.s) + tools::smul_in_g1(xi))
+    let mut terms = [label_basis(l).inner_product(&ski.s), tools::smul_in_g1(xi)];
+    let res = CypherText(terms[0] + terms[1]);
+
+    // wipe the mask and the data term
+    tools::wipe(&mut terms);
+    res
 }
 
-/// Decrypt the given cyphertexts with a given label and decryption key.
-/// - `c`  : cyphertexts
+/// Encrypts a zero contribution for a given label and encryption key. The
+/// cyphertext reduces to the mask `<u, s>`, which must still be sent for the
+/// masks of all clients to cancel out at decryption; only the multiplication
+/// of the generator by `xi` is skipped. Give the same result as
+/// `encrypt(&Scalar::zero(), ski, l)`.
+/// - `ski` : encryption key
+/// - `l`   : label
+pub fn encrypt_zero(ski: &PrivateKey, l: &Label) -> CypherText {
+    CypherText(label_basis(l).inner_product(&ski.s))
+}
+
+/// Encrypts a bit for a given label and encryption key, e.g. to count the
+/// clients satisfying a predicate: decrypting with `y = (1, ..., 1)` gives
+/// the number of ones, which is at most the number of clients and is easily
+/// recovered by testing the candidates with `verify_decrypt`. Skip the
+/// scalar multiplication of the generator: a zero reduces to the mask
+/// (see `encrypt_zero`) and a one adds the generator to it.
+/// - `bit` : contribution
+/// - `ski` : encryption key
+/// - `l`   : label
+pub fn encrypt_bit(bit: bool, ski: &PrivateKey, l: &Label) -> CypherText {
+    let mut mask = [encrypt_zero(ski, l).0];
+    let res = if bit {
+        CypherText(mask[0] + G1Projective::generator())
+    } else {
+        CypherText(mask[0])
+    };
+
+    // wipe the mask
+    tools::wipe(&mut mask);
+    res
+}
+
+/// Encrypts the data of a client `i` under each of the given labels, e.g. to
+/// publish the same value in overlapping time windows. The data term
+/// `xi.g1` is computed once and shared by all cyphertexts. Give the same
+/// result as calling `encrypt` for each label.
+/// - `xi`      : contribution
+/// - `ski`     : encryption key
+/// - `labels`  : labels
+pub fn encrypt_batch_labels(xi: &Scalar, ski: &PrivateKey, labels: &[Label]) -> Vec<CypherText> {
+    encrypt_lazy(xi, ski, labels).collect()
+}
+
+/// Lazy version of `encrypt_batch_labels`: each cyphertext is only computed
+/// when the iterator reaches it, so that cyphertexts can be streamed without
+/// being all held in memory. The data term `xi.g1` is computed once, when
+/// calling this function, and wiped when the iterator is dropped.
+/// - `xi`      : contribution
+/// - `ski`     : encryption key
+/// - `labels`  : labels
+pub fn encrypt_lazy<'a>(
+    xi: &Scalar,
+    ski: &'a PrivateKey,
+    labels: &'a [Label],
+) -> impl Iterator<Item = CypherText> + 'a {
+    let data = tools::Wiped(tools::smul_in_g1(xi));
+    labels.iter().map(move |l| {
+        let mut mask = [label_basis(l).inner_product(&ski.s)];
+        let res = CypherText(mask[0] + *data);
+        tools::wipe(&mut mask);
+        res
+    })
+}
+
+/// Encrypts the data of a client `i` for a given label and encryption key.
+/// Also returns the intermediate terms of the encryption.
+/// - `xi`  : contribution
+/// - `ski` : encryption key
+/// - `l`   : label
+pub fn encrypt_with_aux(xi: &Scalar, ski: &PrivateKey, l: &Label) -> (CypherText, EncryptionAux) {
+    let u = label_basis(l);
+    let mask = u.inner_product(&ski.s);
+    let mut data = [tools::smul_in_g1(xi)];
+    let res = CypherText(mask + data[0]);
+
+    // wipe the data term, the mask is returned to the caller
+    tools::wipe(&mut data);
+    (res, EncryptionAux { u, mask })
+}
+
+/// Encrypts the data of a client `i` for a given label and encryption key,
+/// binding the given associated data (e.g. a schema version) to the
+/// cyphertext.
+/// - `xi`  : contribution
+/// - `ski` : encryption key
+/// - `l`   : label
+/// - `aad` : associated data
+pub fn encrypt_with_aad(xi: &Scalar, ski: &PrivateKey, l: &Label, aad: &[u8]) -> CypherText {
+    encrypt(xi, ski, &aad_label(l, aad))
+}
+
+/// DMCFE cyphertext bound to a recipient:
+/// - `c`:  cyphertext masked with the recipient mask
+/// - `e`:  ephemeral public key `r.g1`
+#[derive(Clone, Copy)]
+pub struct TargetedCypherText {
+    c: CypherText,
+    e: G1Projective,
+}
+
+impl TargetedCypherText {
+    /// Remove the recipient mask and return the DMCFE cyphertext. Using
+    /// another private key than the one of the recipient gives a cyphertext
+    /// which decrypts to a random value.
+    /// - `sk`  : recipient private key
+    /// - `l`   : label
+    pub fn open(&self, sk: &dsum::PrivateKey, l: &Label) -> CypherText {
+        let mut shared = [self.e * **sk];
+        let res = CypherText(self.c.0 - recipient_mask(&shared[0], l));
+        tools::wipe(&mut shared);
+        res
+    }
+}
+
+/// Derive the recipient mask from the ECDH shared point and the label.
+/// - `shared`  : shared point `r.pk = sk.e`
+/// - `l`       : label
+fn recipient_mask(shared: &G1Projective, l: &Label) -> G1Projective {
+    let mut m = b"RecipientMask".to_vec();
+    m.extend_from_slice(&G1Affine::from(shared).to_compressed());
+    m.extend_from_slice(l.canonical_bytes());
+    tools::hash_to_curve(&m)
+}
+
+/// Encrypts the data of a client `i` for a given label and encryption key,
+/// and binds the cyphertext to a recipient by adding a mask derived from an
+/// ECDH exchange between an ephemeral key and the recipient public key.
+///
+/// This is an access control layer on top of the DMCFE scheme, not a change
+/// to the scheme itself: only the recipient can `open` the cyphertext, after
+/// which it is a regular DMCFE cyphertext decrypted by any holder of a
+/// decryption key. Recipient keys are generated using `dsum::client_setup`.
+/// - `recipient_pk`    : recipient public key
+/// - `xi`              : contribution
+/// - `ski`             : encryption key
+/// - `l`               : label
+/// - `rng`             : random number generator
+pub fn encrypt_for<R: CryptoRng + RngCore>(
+    recipient_pk: &dsum::PublicKey,
+    xi: &Scalar,
+    ski: &PrivateKey,
+    l: &Label,
+    rng: &mut R,
+) -> TargetedCypherText {
+    let mut r = [tools::random_scalar(rng)];
+    let mut shared = [**recipient_pk * r[0]];
+    let CypherText(c) = encrypt(xi, ski, l);
+    let res = TargetedCypherText {
+        c: CypherText(c + recipient_mask(&shared[0], l)),
+        e: tools::smul_in_g1(&r[0]),
+    };
+
+    // wipe the ephemeral secret key and the shared point
+    tools::wipe(&mut r);
+    tools::wipe(&mut shared);
+    res
+}
+
+/// Compute the masking term `Sum(e(ui, di))` of the decryption, given the
+/// cyphertext basis.
+/// - `u`  : cyphertext basis
+/// - `dk` : decryption key
+fn mask_term(u: &DVec<G1Projective>, dk: &DecryptionKey) -> Gt {
+    u.iter()
+        .zip(dk.d.iter())
+        .map(|(ui, di)| pairing(&G1Affine::from(ui), &G2Affine::from(di)))
+        .sum::<Gt>()
+}
+
+/// Compute the masking term `Sum(e(ui, di))` of the decryption. It only
+/// depends on the decryption key and the label: it can be computed by a
+/// different party than the data term.
 /// - `dk` : decryption key
 /// - `l`  : label
-pub fn decrypt(c: &[CypherText], dk: &DecryptionKey, l: &Label) -> Gt {
-    let u = DVec::from(tools::double_hash_to_curve_in_g1(l));
+pub fn decrypt_mask_term(dk: &DecryptionKey, l: &Label) -> Gt {
+    mask_term(&label_basis(l), dk)
+}
 
+/// Compute the data term `Sum(e(ci, yi.g2))` of the decryption. The
+/// decryption result is `data_term - mask_term`.
+/// - `c`  : cyphertexts
+/// - `dk` : decryption key
+pub fn decrypt_data_term(c: &[CypherText], dk: &DecryptionKey) -> Gt {
     c.iter()
         .zip(dk.y.iter())
         .map(|(CypherText(ci), yi)| {
             pairing(&G1Affine::from(ci), &G2Affine::from(tools::smul_in_g2(yi)))
         })
         .sum::<Gt>()
+}
+
+/// Check that one cyphertext is given per coefficient of the decryption
+/// function: missing cyphertexts would silently give a wrong result.
+/// - `n`   : number of cyphertexts
+/// - `y`   : decryption function
+fn check_cyphertext_count(n: usize, y: &[Scalar]) -> Result<()> {
+    eyre::ensure!(
+        n == y.len(),
+        "Wrong number of cyphertexts: {} instead of {}!",
+        n,
+        y.len()
+    );
+    Ok(())
+}
+
+/// Decrypt the given cyphertexts with a given label and decryption key. Fail
+/// if the number of cyphertexts does not match the decryption key.
+/// - `c`  : cyphertexts
+/// - `dk` : decryption key
+/// - `l`  : label
+pub fn decrypt(c: &[CypherText], dk: &DecryptionKey, l: &Label) -> Result<Gt> {
+    check_cyphertext_count(c.len(), &dk.y)?;
+    Ok(decrypt_data_term(c, dk) - decrypt_mask_term(dk, l))
+}
+
+/// Decryption result tagged with the inputs which produced it, so that logs
+/// and audits can attribute it:
+/// - `value`   : decryption result
+/// - `label`   : label of the cyphertexts
+/// - `y_digest`: digest of the decryption function (see `function_digest`)
+#[derive(Clone)]
+pub struct DecryptResult {
+    pub value: Gt,
+    pub label: Label,
+    pub y_digest: [u8; 32],
+}
+
+/// Compute the digest of a decryption function: `H(y)`. It identifies the
+/// function without storing it.
+/// - `y`   : decryption function
+pub fn function_digest(y: &[Scalar]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"FunctionDigest");
+    hasher.update(Label::from(y).canonical_bytes());
+    let mut res = [0; 32];
+    res.copy_from_slice(&hasher.finalize());
+    res
+}
+
+/// Decrypt the given cyphertexts with a given label and decryption key, and
+/// tag the result with the label and the digest of the decryption function.
+/// - `c`  : cyphertexts
+/// - `dk` : decryption key
+/// - `l`  : label
+pub fn decrypt_tagged(c: &[CypherText], dk: &DecryptionKey, l: &Label) -> Result<DecryptResult> {
+    Ok(DecryptResult {
+        value: decrypt(c, dk, l)?,
+        label: l.clone(),
+        y_digest: function_digest(&dk.y),
+    })
+}
+
+/// Decrypt the cyphertexts received so far, e.g. to give an early estimate.
+/// This is NOT the decryption result until the cyphertexts of all clients
+/// are given: the masking term is that of the whole cohort, so it only
+/// cancels out with the terms of all the cyphertexts. Adding the data terms
+/// of the missing cyphertexts (see `decrypt_data_term`) gives the final
+/// result. Fail if a client ID is out of range or duplicated.
+/// - `available`   : cyphertexts received so far, along with the client IDs
+/// - `dk`          : decryption key
+/// - `l`           : label
+pub fn decrypt_partial(
+    available: &[(CypherText, usize)],
+    dk: &DecryptionKey,
+    l: &Label,
+) -> Result<Gt> {
+    let mut ids = HashSet::with_capacity(available.len());
+    let mut res = -decrypt_mask_term(dk, l);
+    for (CypherText(ci), id) in available {
+        let yi =
+            dk.y.get(*id)
+                .ok_or_else(|| eyre::eyre!("Unexpected client ID {}!", id))?;
+        eyre::ensure!(ids.insert(*id), "Duplicated client ID {}!", id);
+        res += pairing(&G1Affine::from(ci), &G2Affine::from(tools::smul_in_g2(yi)));
+    }
+    Ok(res)
+}
+
+/// Decrypt the given cyphertexts, each received along with the label used to
+/// encrypt it. Fail if the labels differ: all clients must agree on the
+/// label, e.g. on a timestamp given by `Label::new()`, otherwise the result is
+/// incorrect.
+/// - `c`  : cyphertexts, along with their labels
+/// - `dk` : decryption key
+pub fn decrypt_labelled(c: &[(CypherText, Label)], dk: &DecryptionKey) -> Result<Gt> {
+    let (_, l) = c
+        .first()
+        .ok_or_else(|| eyre::eyre!("No cyphertext to decrypt!"))?;
+    eyre::ensure!(
+        c.iter()
+            .all(|(_, li)| li.canonical_bytes() == l.canonical_bytes()),
+        "Cyphertexts are using different labels!"
+    );
+    let c = c.iter().map(|(ci, _)| *ci).collect::<Vec<_>>();
+    decrypt(&c, dk, l)
+}
+
+/// Decrypt the given cyphertexts with a given label and decryption key, using
+/// the given cache to avoid recomputing the cyphertext basis of recently seen
+/// labels.
+/// - `c`    : cyphertexts
+/// - `dk`   : decryption key
+/// - `l`    : label
+/// - `cache`: cyphertext basis cache
+pub fn decrypt_cached(
+    c: &[CypherText],
+    dk: &DecryptionKey,
+    l: &Label,
+    cache: &LabelBasisCache,
+) -> Result<Gt> {
+    check_cyphertext_count(c.len(), &dk.y)?;
+    Ok(decrypt_data_term(c, dk) - mask_term(&cache.get(l), dk))
+}
+
+/// Decrypt the given cyphertexts with a given label and decryption key. Each
+/// cyphertext is matched to its coefficient in the decryption function using
+/// its client ID, so that the cyphertexts can be processed in any order (e.g.
+/// sorted by their bytes in order not to leak which client sent which
+/// cyphertext).
+/// - `c`  : cyphertexts, along with the ID of the client who sent them
+/// - `dk` : decryption key
+/// - `l`  : label
+pub fn decrypt_by_id(c: &[ClientIndexed<CypherText>], dk: &DecryptionKey, l: &Label) -> Result<Gt> {
+    check_cyphertext_count(c.len(), &dk.y)?;
+    let mut ids = HashSet::with_capacity(c.len());
+    let mut res = Gt::identity();
+    for ci in c {
+        eyre::ensure!(
+            ci.id() < dk.y.len() && ids.insert(ci.id()),
+            "Unexpected or duplicated client ID {}!",
+            ci.id()
+        );
+        res += pairing(
+            &G1Affine::from(ci.value().0),
+            &G2Affine::from(tools::smul_in_g2(&dk.y[ci.id()])),
+        );
+    }
+    Ok(res - decrypt_mask_term(dk, l))
+}
+
+/// Decrypt the given cyphertexts, encrypted using `encrypt_with_aad`, with a
+/// given label, associated data and decryption key.
+///
+/// Cyphertexts produced with different associated data decrypt to a random
+/// group element instead of `<x,y>.gT`: the mismatch is detected when the
+/// discrete logarithm cannot be found in the expected range.
+///
+/// - `c`  : cyphertexts
+/// - `dk` : decryption key
+/// - `l`  : label
+/// - `aad`: associated data
+pub fn decrypt_with_aad(c: &[CypherText], dk: &DecryptionKey, l: &Label, aad: &[u8]) -> Result<Gt> {
+    decrypt(c, dk, &aad_label(l, aad))
+}
+
+/// Decrypt the given serialized cyphertexts with a given label and decryption
+/// key. Each cyphertext is deserialized inline, which avoids allocating the
+/// intermediate cyphertext list when reading from network buffers.
+/// - `raw`: compressed cyphertexts
+/// - `dk` : decryption key
+/// - `l`  : label
+pub fn decrypt_from_bytes(raw: &[&[u8]], dk: &DecryptionKey, l: &Label) -> Result<Gt> {
+    check_cyphertext_count(raw.len(), &dk.y)?;
+    let mut res = Gt::identity();
+    for (ci, yi) in raw.iter().zip(dk.y.iter()) {
+        let CypherText(ci) = CypherText::from_bytes(ci)?;
+        res += pairing(&G1Affine::from(ci), &G2Affine::from(tools::smul_in_g2(yi)));
+    }
+    Ok(res - decrypt_mask_term(dk, l))
+}
+
+/// Remove the shift introduced by rerandomized cyphertexts from a decryption
+/// result: `res - Sum(yi.ri).gT`.
+/// - `res`     : decryption result
+/// - `y`       : decryption function
+/// - `offsets` : offsets `ri` returned by `CypherText::rerandomize`
+pub fn remove_offsets(res: &Gt, y: &[Scalar], offsets: &[Scalar]) -> Result<Gt> {
+    eyre::ensure!(
+        y.len() == offsets.len(),
+        "Wrong number of offsets: {} instead of {}!",
+        offsets.len(),
+        y.len()
+    );
+    let shift = y
+        .iter()
+        .zip(offsets.iter())
+        .map(|(yi, ri)| yi * ri)
+        .sum::<Scalar>();
+    Ok(res - Gt::generator() * shift)
+}
+
+/// Check a claimed decryption result: `gt == v.gT`. The decryptor can publish
+/// the decryption result `gt` along with the value `v` it recovered from it,
+/// and any party can check the claim without the decryption key.
+/// - `gt`  : decryption result
+/// - `v`   : claimed inner product
+pub fn verify_decrypt(gt: &Gt, v: u64) -> bool {
+    *gt == tools::gt_pow(&Gt::generator(), v)
+}
+
+/// Recover `v` from `gt = v.gT` by walking `0, gT, 2.gT, ...`. This is only
+/// suited to small bounds, e.g. counts or small sums. The identity and the
+/// generator, i.e. an empty or a unit result, are checked first.
+///
+/// The walk stops after at most `bound + 1` comparisons and `bound - 1`
+/// additions in Gt, whatever `gt` is: a result out of `[0, bound]`, e.g. a
+/// residual mask left by a broken setup, returns `None` after the full walk.
+/// - `gt`      : element of Gt
+/// - `bound`   : upper bound on `v`
+fn solve_bounded(gt: &Gt, bound: u64) -> Option<u64> {
+    if *gt == Gt::identity() {
+        return Some(0);
+    }
+    if bound == 0 {
+        return None;
+    }
+    if *gt == Gt::generator() {
+        return Some(1);
+    }
+    let mut acc = Gt::generator() + Gt::generator();
+    for v in 2..=bound {
+        if acc == *gt {
+            return Some(v);
+        }
+        acc += Gt::generator();
+    }
+    None
+}
+
+/// Decrypt the given cyphertexts and recover the inner product `v` from the
+/// decryption result `gt = v.gT`, along with `gt` itself so that the claim can
+/// be published and checked using `verify_decrypt`. The search costs up to
+/// `bound` additions in Gt. Fail if the inner product is not in `[0, bound]`.
+/// - `c`       : cyphertexts
+/// - `dk`      : decryption key
+/// - `l`       : label
+/// - `bound`   : upper bound on the inner product
+pub fn decrypt_verifiable(
+    c: &[CypherText],
+    dk: &DecryptionKey,
+    l: &Label,
+    bound: u64,
+) -> Result<(u64, Gt)> {
+    let gt = decrypt(c, dk, l)?;
+    let v = solve_bounded(&gt, bound)
+        .ok_or_else(|| eyre::eyre!("The inner product is not in [0, {}]!", bound))?;
+    Ok((v, gt))
+}
+
+/// Decrypt the given cyphertexts and recover the inner product as an integer.
+/// `sum` tracks the integer bound of the inner product (see
+/// `BoundedScalarSum`): fail before decrypting if the search up to `bound`
+/// does not cover it, since the inner product could then be missed.
+/// - `c`       : cyphertexts
+/// - `dk`      : decryption key
+/// - `l`       : label
+/// - `bound`   : upper bound of the search
+/// - `sum`     : bounded sum of the products `yi.xi`
+pub fn decrypt_to_scalar(
+    c: &[CypherText],
+    dk: &DecryptionKey,
+    l: &Label,
+    bound: u64,
+    sum: &BoundedScalarSum,
+) -> Result<u64> {
+    eyre::ensure!(
+        sum.bound() <= u128::from(bound),
+        "The search bound {} does not cover the bound {} of the sum!",
+        bound,
+        sum.bound()
+    );
+    decrypt_verifiable(c, dk, l, bound).map(|(v, _)| v)
+}
+
+/// Outcome of `decrypt_diagnose`:
+/// - `Ok`                  : the inner product recovered from the result
+/// - `ResidualDetected`    : the result is not `v.gT` for any `v` in the bound
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecryptOutcome {
+    Ok(u64),
+    ResidualDetected,
+}
+
+/// Decrypt the given cyphertexts and try to recover the inner product in
+/// `[0, bound]`. If the `T` matrices of the cohort do not sum to zero, e.g.
+/// because of a setup error or a dishonest client, the masks do not cancel
+/// out and the result is a random element of Gt, reported as a residual.
+/// The bound should cover all the valid inner products: an inner product
+/// above it is reported as a residual too. Fail if the cyphertexts cannot be
+/// decrypted at all.
+/// - `c`       : cyphertexts
+/// - `dk`      : decryption key
+/// - `l`       : label
+/// - `bound`   : upper bound on the inner product
+pub fn decrypt_diagnose(
+    c: &[CypherText],
+    dk: &DecryptionKey,
+    l: &Label,
+    bound: u64,
+) -> Result<DecryptOutcome> {
+    Ok(solve_bounded(&decrypt(c, dk, l)?, bound)
+        .map_or(DecryptOutcome::ResidualDetected, DecryptOutcome::Ok))
+}
+
+/// Reduce a recovered inner product modulo a small public modulus `p`.
+/// Fail if `p` is zero.
+/// - `value`   : recovered inner product
+/// - `p`       : modulus
+pub fn reduce_mod(value: u64, p: u64) -> Result<u64> {
+    eyre::ensure!(p != 0, "The modulus should not be zero!");
+    Ok(value % p)
+}
+
+/// Decrypt the given cyphertexts and return the inner product modulo `p`.
+///
+/// The scheme cannot reduce the inner product before decryption: the integer
+/// `<x, y>` itself must be recovered, so it should stay in `[0, bound]`
+/// whatever the inputs. Choose the components of `y` in `[0, p)`, and bound
+/// the inputs `x`: for `n` inputs in `[0, b]`, the inner product is at most
+/// `n.b.(p - 1)`, which gives the search bound to use (see
+/// `BoundedScalarSum`). Reducing `y` modulo `p` beforehand does not change the
+/// result modulo `p`.
+/// - `c`       : cyphertexts
+/// - `dk`      : decryption key
+/// - `l`       : label
+/// - `bound`   : upper bound on the unreduced inner product
+/// - `p`       : modulus
+pub fn decrypt_mod(
+    c: &[CypherText],
+    dk: &DecryptionKey,
+    l: &Label,
+    bound: u64,
+    p: u64,
+) -> Result<u64> {
+    eyre::ensure!(p != 0, "The modulus should not be zero!");
+    reduce_mod(decrypt_verifiable(c, dk, l, bound)?.0, p)
+}
+
+/// Result of the DMCFE key distribution health check:
+/// - `t_sum`       : the `T` matrices sum to zero
+/// - `s_nonzero`   : no component of the `s` vectors is zero
+/// - `round_trip`  : a test encryption is correctly decrypted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HealthReport {
+    pub t_sum: bool,
+    pub s_nonzero: bool,
+    pub round_trip: bool,
+}
+
+impl HealthReport {
+    /// Return `true` if all checks passed.
+    pub fn is_healthy(&self) -> bool {
+        self.t_sum && self.s_nonzero && self.round_trip
+    }
+}
+
+/// Check the consistency of a freshly distributed set of DMCFE keys, using
+/// the default configuration. This is an in-process diagnostic: it requires
+/// the private keys of all clients and should never be run by an untrusted
+/// party.
+/// - `keys`: private keys of all the clients, ordered by client ID
+pub fn healthcheck(keys: &[PrivateKey]) -> Result<HealthReport> {
+    Config::default().healthcheck(keys)
+}
+
+/// Incremental DMCFE decryption: the pairing terms of the cyphertexts are
+/// accumulated as they arrive, so that they do not need to be buffered.
+pub struct DecryptAccumulator<'a> {
+    /// - `dk`:     decryption key
+    dk: &'a DecryptionKey,
+    /// - `mask`:   masking term of the decryption
+    mask: Gt,
+    /// - `acc`:    data term accumulated so far
+    acc: Gt,
+    /// - `ids`:    IDs of the clients whose cyphertext was accumulated
+    ids: HashSet<usize>,
+}
+
+impl<'a> DecryptAccumulator<'a> {
+    /// Start the decryption of the cyphertexts encrypted with the given
+    /// label, using the given decryption key.
+    /// - `dk`  : decryption key
+    /// - `l`   : label
+    pub fn new(dk: &'a DecryptionKey, l: &Label) -> Self {
+        Self {
+            dk,
+            mask: decrypt_mask_term(dk, l),
+            acc: Gt::identity(),
+            ids: HashSet::with_capacity(dk.y.len()),
+        }
+    }
+
+    /// Accumulate the cyphertext of the given client.
+    /// - `id`  : client ID
+    /// - `ci`  : cyphertext
+    pub fn push(&mut self, id: usize, ci: &CypherText) -> Result<()> {
+        eyre::ensure!(
+            id < self.dk.y.len() && !self.ids.contains(&id),
+            "Unexpected or duplicated client ID {}!",
+            id
+        );
+        self.acc += pairing(
+            &G1Affine::from(ci.0),
+            &G2Affine::from(tools::smul_in_g2(&self.dk.y[id])),
+        );
+        self.ids.insert(id);
+        Ok(())
+    }
+
+    /// Return the decryption result. Fail if some cyphertexts are missing.
+    pub fn finalize(self) -> Result<Gt> {
+        eyre::ensure!(
+            self.ids.len() == self.dk.y.len(),
+            "Missing cyphertexts: {} received instead of {}!",
+            self.ids.len(),
+            self.dk.y.len()
+        );
+        Ok(self.acc - self.mask)
+    }
+}
+
+/// Sliding window DMCFE decryption over successive labels, e.g. for time
+/// series: it keeps the sum of the decryption results of the last `w` labels.
+/// Each step only decrypts the cyphertexts of the new label, and removes the
+/// result of the label which fell out of the window.
+pub struct SlidingDecryptor<'a> {
+    /// - `dk`:     decryption key
+    dk: &'a DecryptionKey,
+    /// - `w`:      window size
+    w: usize,
+    /// - `window`: decryption results of the labels in the window
+    window: VecDeque<Gt>,
+    /// - `sum`:    sum of the decryption results in the window
+    sum: Gt,
+}
+
+impl<'a> SlidingDecryptor<'a> {
+    /// Start a sliding window decryption over `w` labels. Fail if `w` is zero.
+    /// - `dk`  : decryption key
+    /// - `w`   : window size
+    pub fn new(dk: &'a DecryptionKey, w: usize) -> Result<Self> {
+        eyre::ensure!(w > 0, "The window size should not be zero!");
+        Ok(Self {
+            dk,
+            w,
+            window: VecDeque::with_capacity(w + 1),
+            sum: Gt::identity(),
+        })
+    }
+
+    /// Decrypt the cyphertexts of a new label and slide the window. Return
+    /// the sum of the decryption results of the labels in the window.
+    /// - `c`   : cyphertexts of the new label
+    /// - `l`   : new label
+    pub fn advance(&mut self, c: &[CypherText], l: &Label) -> Result<Gt> {
+        let res = decrypt(c, self.dk, l)?;
+        self.sum += res;
+        self.window.push_back(res);
+        if self.window.len() > self.w {
+            if let Some(old) = self.window.pop_front() {
+                self.sum -= old;
+            }
+        }
+        Ok(self.sum)
+    }
+}
+
+// G2 variant of the scheme: the cyphertexts are in G2 and the decryption keys
+// in G1. Since G2 operations are more expensive than G1 ones, the default G1
+// variant gives cheaper encryptions and smaller cyphertexts, while the G2
+// variant gives cheaper partial decryption keys and decryptions (the `yi` are
+// multiplied with the G1 generator). Prefer the G2 variant when the
+// decryptor's workload dominates, e.g. many decryption functions are requested.
+
+/// DMCFE cyphertext type, in the G2 variant
+#[derive(Clone, Copy)]
+pub struct CypherTextG2(G2Projective);
+
+/// DMCFE partial decryption key type, in the G2 variant
+#[derive(Clone)]
+pub struct PartialDecryptionKeyG1(DVec<G1Projective>);
+
+/// DMCFE decryption key type, in the G2 variant
+#[derive(Clone)]
+pub struct DecryptionKeyG1 {
+    /// - `y`:  decryption function
+    pub y: Vec<Scalar>,
+    /// - `d`:  functional decryption key
+    d: DVec<G1Projective>,
+}
+
+/// Encrypts the data of a client `i` for a given label and encryption key, in
+/// the G2 variant: the cyphertext can only be decrypted using `decrypt_g1`.
+/// - `xi`  : contribution
+/// - `ski` : encryption key
+/// - `l`   : label
+pub fn encrypt_g2(xi: &Scalar, ski: &PrivateKey, l: &Label) -> CypherTextG2 {
+    let u = DVec::from(tools::double_hash_to_curve_in_g2(l.canonical_bytes()));
+    let mut terms = [u.inner_product(&ski.s), tools::smul_in_g2(xi)];
+    let res = CypherTextG2(terms[0] + terms[1]);
+
+    // wipe the mask and the data term
+    tools::wipe(&mut terms);
+    res
+}
+
+/// Compute the DMCFE partial decryption key, in the G2 variant.
+/// - `id`  : client ID
+/// - `ski` : private key
+/// - `y`   : decryption function
+pub fn dkey_gen_share_g1(id: usize, ski: &PrivateKey, y: &[Scalar]) -> PartialDecryptionKeyG1 {
+    let v = DVec::from(tools::double_hash_to_curve_in_g1(
+        Label::from(y).canonical_bytes(),
+    ));
+    let mut sy = &ski.s * &y[id];
+    let mut syg = &sy * &G1Projective::generator();
+    let mut tv = &ski.t * &v;
+    let res = PartialDecryptionKeyG1(syg.clone() + &tv);
+
+    // wipe the secret-derived terms
+    tools::wipe(&mut sy[..]);
+    tools::wipe(&mut syg[..]);
+    tools::wipe(&mut tv[..]);
+    res
+}
+
+/// Combine the partial decryption keys to return the final decryption key, in
+/// the G2 variant, using the default configuration.
+/// - `y`   : decryption function
+/// - `pdk` : partial decryption keys
+pub fn key_comb_g1(y: &[Scalar], pdk: &[PartialDecryptionKeyG1]) -> Result<DecryptionKeyG1> {
+    Config::default().key_comb_g1(y, pdk)
+}
+
+/// Decrypt the given cyphertexts, encrypted using `encrypt_g2`, with a given
+/// label and decryption key. Fail if the number of cyphertexts does not match
+/// the decryption key.
+/// - `c`  : cyphertexts
+/// - `dk` : decryption key
+/// - `l`  : label
+pub fn decrypt_g1(c: &[CypherTextG2], dk: &DecryptionKeyG1, l: &Label) -> Result<Gt> {
+    check_cyphertext_count(c.len(), &dk.y)?;
+    let u = DVec::from(tools::double_hash_to_curve_in_g2(l.canonical_bytes()));
+    Ok(c.iter()
+        .zip(dk.y.iter())
+        .map(|(CypherTextG2(ci), yi)| {
+            pairing(&G1Affine::from(tools::smul_in_g1(yi)), &G2Affine::from(ci))
+        })
+        .sum::<Gt>()
         - u.iter()
             .zip(dk.d.iter())
-            .map(|(ui, di)| pairing(&G1Affine::from(ui), &G2Affine::from(di)))
-            .sum::<Gt>()
+            .map(|(ui, di)| pairing(&G1Affine::from(di), &G2Affine::from(ui)))
+            .sum::<Gt>())
+}
+
+/// Thread-safe LRU cache of the cyphertext bases `u = H(l)`, indexed by the
+/// canonical bytes of the labels.
+pub struct LabelBasisCache {
+    /// - `capacity`:   maximum number of cached bases
+    capacity: usize,
+    /// - `inner`:      cached bases, from the least to the most recently used
+    inner: Mutex<LabelBasisCacheInner>,
+}
+
+/// Mutable state of a `LabelBasisCache`
+#[derive(Default)]
+struct LabelBasisCacheInner {
+    bases: VecDeque<(Vec<u8>, DVec<G1Projective>)>,
+    hits: usize,
+    misses: usize,
+}
+
+impl LabelBasisCache {
+    /// Create a new empty cache.
+    /// - `capacity`: maximum number of cached bases
+    pub fn new(capacity: usize) -> Result<Self> {
+        eyre::ensure!(capacity > 0, "The cache capacity cannot be zero!");
+        Ok(Self {
+            capacity,
+            inner: Mutex::default(),
+        })
+    }
+
+    /// Return the cyphertext basis associated to the given label, computing
+    /// and caching it if needed. The least recently used basis is evicted when
+    /// the cache is full.
+    /// - `l`   : label
+    pub fn get(&self, l: &Label) -> DVec<G1Projective> {
+        let mut inner = self
+            .inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let bytes = l.canonical_bytes();
+        if let Some(pos) = inner.bases.iter().position(|(key, _)| key == bytes) {
+            inner.hits += 1;
+            let entry = inner.bases.remove(pos).expect("position is valid");
+            let u = entry.1.clone();
+            inner.bases.push_back(entry);
+            return u;
+        }
+        inner.misses += 1;
+        let u = label_basis(l);
+        if inner.bases.len() == self.capacity {
+            inner.bases.pop_front();
+        }
+        inner.bases.push_back((bytes.to_vec(), u.clone()));
+        u
+    }
+
+    /// Return the number of requests served from the cache.
+    pub fn hits(&self) -> usize {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .hits
+    }
+
+    /// Return the number of bases computed by the cache.
+    pub fn misses(&self) -> usize {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .misses
+    }
+}
+
+/// DMCFE decryptor session: gathers the cyphertexts and the partial
+/// decryption keys sent by the clients, indexed by client ID, in any order.
+#[derive(Clone, Default)]
+pub struct DecryptorSession {
+    /// - `c`:   received cyphertexts
+    c: HashMap<usize, CypherText>,
+    /// - `pdk`: received partial decryption keys
+    pdk: HashMap<usize, PartialDecryptionKey>,
+    /// - `config`: configuration used to combine the partial decryption keys
+    config: Config,
+}
+
+impl DecryptorSession {
+    /// Store the cyphertext of the client with the given ID. Fail if a
+    /// cyphertext was already received from this client: a late or replayed
+    /// message must not replace the first one.
+    /// - `id`  : client ID
+    /// - `ci`  : cyphertext
+    pub fn add_cyphertext(&mut self, id: usize, ci: CypherText) -> Result<()> {
+        eyre::ensure!(
+            !self.c.contains_key(&id),
+            "A cyphertext was already received from client {}!",
+            id
+        );
+        self.c.insert(id, ci);
+        Ok(())
+    }
+
+    /// Store the partial decryption key of the client with the given ID. Fail
+    /// if a partial decryption key was already received from this client.
+    /// - `id`  : client ID
+    /// - `pdki`: partial decryption key
+    pub fn add_partial_key(&mut self, id: usize, pdki: PartialDecryptionKey) -> Result<()> {
+        eyre::ensure!(
+            !self.pdk.contains_key(&id),
+            "A partial decryption key was already received from client {}!",
+            id
+        );
+        self.pdk.insert(id, pdki);
+        Ok(())
+    }
+
+    /// Return `true` if both the cyphertext and the partial decryption key of
+    /// every expected client have been received.
+    /// - `expected_ids`: IDs of the participating clients
+    pub fn is_complete(&self, expected_ids: &HashSet<usize>) -> bool {
+        expected_ids
+            .iter()
+            .all(|id| self.c.contains_key(id) && self.pdk.contains_key(id))
+    }
+
+    /// Combine the partial decryption keys and decrypt the cyphertexts. The
+    /// client IDs should be the indices of the decryption function.
+    /// - `y`   : decryption function
+    /// - `l`   : label
+    pub fn decrypt(&self, y: &[Scalar], l: &Label) -> Result<Gt> {
+        let expected_ids = (0..y.len()).collect::<HashSet<_>>();
+        eyre::ensure!(
+            self.is_complete(&expected_ids),
+            "Missing contributions: {} cyphertexts and {} partial decryption keys received instead of {}!",
+            self.c.len(),
+            self.pdk.len(),
+            y.len()
+        );
+        eyre::ensure!(
+            self.c.len() == y.len() && self.pdk.len() == y.len(),
+            "Received contributions from unexpected clients!"
+        );
+        let c = (0..y.len()).map(|id| self.c[&id]).collect::<Vec<_>>();
+        let pdk = (0..y.len())
+            .map(|id| self.pdk[&id].clone())
+            .collect::<Vec<_>>();
+        decrypt(&c, &self.config.key_comb(y, &pdk)?, l)
+    }
+}
+
+/// DMCFE decryption key combined on first use. It stores the partial
+/// decryption keys and only combines them, and precomputes the `yi.g2`
+/// terms, when `decrypt` is first called: no work is wasted on functions
+/// which are never used. Later decryptions reuse the cached key. The key is
+/// `Sync`: it can be shared by several decryptor threads.
+#[derive(Clone)]
+pub struct LazyDecryptionKey {
+    /// - `y`   : decryption function
+    y: Vec<Scalar>,
+    /// - `pdk` : partial decryption keys
+    pdk: Vec<PartialDecryptionKey>,
+    /// - `config`: configuration used to combine the partial decryption keys
+    config: Config,
+    /// - `dk`  : combined decryption key along with the `yi.g2` terms
+    dk: OnceLock<(DecryptionKey, Vec<G2Affine>)>,
+}
+
+impl LazyDecryptionKey {
+    /// Store the partial decryption keys, without combining them. They are
+    /// combined using the default configuration.
+    /// - `y`   : decryption function
+    /// - `pdk` : partial decryption keys
+    pub fn new(y: &[Scalar], pdk: &[PartialDecryptionKey]) -> Self {
+        Config::default().lazy_key(y, pdk)
+    }
+
+    /// Return `true` if the partial decryption keys have been combined.
+    pub fn is_combined(&self) -> bool {
+        self.dk.get().is_some()
+    }
+
+    /// Decrypt the given cyphertexts with a given label, combining the partial
+    /// decryption keys on first call. Fail if the partial decryption keys
+    /// cannot be combined.
+    /// - `c`   : cyphertexts
+    /// - `l`   : label
+    pub fn decrypt(&self, c: &[CypherText], l: &Label) -> Result<Gt> {
+        check_cyphertext_count(c.len(), &self.y)?;
+        let (dk, yg2) = match self.dk.get() {
+            Some(cached) => cached,
+            None => {
+                let dk = self.config.key_comb(&self.y, &self.pdk)?;
+                let yg2 = self
+                    .y
+                    .iter()
+                    .map(|yi| G2Affine::from(tools::smul_in_g2(yi)))
+                    .collect();
+                self.dk.get_or_init(|| (dk, yg2))
+            }
+        };
+        let data_term = c
+            .iter()
+            .zip(yg2.iter())
+            .map(|(CypherText(ci), yi)| pairing(&G1Affine::from(ci), yi))
+            .sum::<Gt>();
+        Ok(data_term - decrypt_mask_term(dk, l))
+    }
 }
@@ -22,7 +22,75 @@ pub struct PrivateKey {
     /// - `s`  : private key
     pub s: Vec<Vec<Scalar>>,
     /// - `msk`: IPFE master secret key
-    msk: Vec<ipfe::PrivateKey>,
+    pub(crate) msk: Vec<ipfe::PrivateKey>,
+}
+
+impl PrivateKey {
+    /// Create an encryption key from its components. Fail if they are not
+    /// consistent (see `validate`).
+    /// - `s`   : `(m, 2)` private key matrix
+    /// - `msk` : IPFE master secret key of length `m`
+    pub fn new(s: Vec<Vec<Scalar>>, msk: Vec<ipfe::PrivateKey>) -> Result<Self> {
+        let res = Self { s, msk };
+        res.validate()?;
+        Ok(res)
+    }
+
+    /// Check the dimensions of the key: the matrix `s` should have one row of
+    /// length 2 per IPFE key element. Inconsistent keys would silently give
+    /// wrong cyphertexts and decryption keys.
+    pub fn validate(&self) -> Result<()> {
+        eyre::ensure!(
+            self.s.len() == self.msk.len(),
+            "Inconsistent encryption key: {} matrix rows for an IPFE key of length {}!",
+            self.s.len(),
+            self.msk.len()
+        );
+        eyre::ensure!(
+            self.s.iter().all(|row| row.len() == 2),
+            "Inconsistent encryption key: all matrix rows should be of length 2!"
+        );
+        Ok(())
+    }
+
+    /// Serialize the encryption key. The matrix `s` and the IPFE key are
+    /// prefixed by their length, encoded as little-endian `u64`:
+    /// `header | m | s | m | msk`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut res = tools::codec::new_buffer(16 + 96 * self.msk.len());
+        res.extend_from_slice(&(self.s.len() as u64).to_le_bytes());
+        self.s
+            .iter()
+            .flatten()
+            .for_each(|sij| res.extend_from_slice(&sij.to_bytes()));
+        res.extend_from_slice(&(self.msk.len() as u64).to_le_bytes());
+        self.msk
+            .iter()
+            .for_each(|&ipfe::PrivateKey(mski)| res.extend_from_slice(&mski.to_bytes()));
+        res
+    }
+
+    /// Deserialize an encryption key serialized using `to_bytes`. Fail if the
+    /// key is not consistent (see `validate`).
+    /// - `bytes`   : serialized encryption key
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        let bytes = &mut bytes;
+        tools::codec::read_header(bytes)?;
+        let m = tools::read_len(bytes, 64)?;
+        let s = (0..m)
+            .map(|_| Ok(vec![tools::read_scalar(bytes)?, tools::read_scalar(bytes)?]))
+            .collect::<Result<Vec<_>>>()?;
+        let k = tools::read_len(bytes, 32)?;
+        let msk = (0..k)
+            .map(|_| tools::read_scalar(bytes).map(ipfe::PrivateKey))
+            .collect::<Result<Vec<_>>>()?;
+        eyre::ensure!(
+            bytes.is_empty(),
+            "{} unexpected trailing bytes in the serialized encryption key!",
+            bytes.len()
+        );
+        Self::new(s, msk)
+    }
 }
 
 /// MCFE decryption key type
@@ -36,6 +104,70 @@ pub struct DecryptionKey {
     pub(crate) ip_dk: Vec<ipfe::DecryptionKey>,
 }
 
+impl DecryptionKey {
+    /// Serialize the decryption key. The matrix `y` is prefixed by its number
+    /// of rows and each row by its length, all encoded as little-endian `u64`:
+    /// `header | n | (m_i | y_i) for i in 0..n | d | ip_dk`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut res = tools::codec::new_buffer(
+            8 + self.y.iter().map(|yi| 8 + 32 * yi.len()).sum::<usize>()
+                + 2 * 32
+                + 32 * self.ip_dk.len(),
+        );
+        res.extend_from_slice(&(self.y.len() as u64).to_le_bytes());
+        for yi in &self.y {
+            res.extend_from_slice(&(yi.len() as u64).to_le_bytes());
+            yi.iter()
+                .for_each(|yij| res.extend_from_slice(&yij.to_bytes()));
+        }
+        self.d
+            .iter()
+            .for_each(|di| res.extend_from_slice(&di.to_bytes()));
+        self.ip_dk
+            .iter()
+            .for_each(|&ipfe::DecryptionKey(dki)| res.extend_from_slice(&dki.to_bytes()));
+        res
+    }
+
+    /// Deserialize a decryption key serialized using `to_bytes`. Fail if the
+    /// rows of `y` do not all have the same length.
+    /// - `bytes`   : serialized decryption key
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        let bytes = &mut bytes;
+        tools::codec::read_header(bytes)?;
+        // each row holds at least its length header
+        let n = tools::read_len(bytes, 8)?;
+        let mut y: Vec<Vec<Scalar>> = Vec::with_capacity(n);
+        for _ in 0..n {
+            let m = tools::read_len(bytes, 32)?;
+            if let Some(y0) = y.first() {
+                eyre::ensure!(
+                    m == y0.len(),
+                    "Ragged decryption function: row {} has {} columns instead of {}!",
+                    y.len(),
+                    m,
+                    y0.len()
+                );
+            }
+            y.push(
+                (0..m)
+                    .map(|_| tools::read_scalar(bytes))
+                    .collect::<Result<Vec<_>>>()?,
+            );
+        }
+        let d = types::DVec::new(tools::read_scalar(bytes)?, tools::read_scalar(bytes)?);
+        let ip_dk = (0..n)
+            .map(|_| tools::read_scalar(bytes).map(ipfe::DecryptionKey))
+            .collect::<Result<Vec<_>>>()?;
+        eyre::ensure!(
+            bytes.is_empty(),
+            "{} unexpected trailing bytes in the serialized decryption key!",
+            bytes.len()
+        );
+        Ok(Self { y, d, ip_dk })
+    }
+}
+
 /// Compute the client encryption keys.
 /// - `m`   : number of contributions per client
 /// - `rng` : random number generator
@@ -52,23 +184,28 @@ pub fn setup<R: CryptoRng + RngCore>(m: usize, rng: &mut R) -> PrivateKey {
 /// - `xi`   : client contribution
 /// - `label`: label
 pub fn encrypt(eki: &PrivateKey, xi: &[Scalar], label: &types::Label) -> Result<Vec<CypherText>> {
+    eki.validate()?;
     eyre::ensure!(
         xi.len() == eki.msk.len(),
         "Input plaintext has wrong dimension: {} instead of {}!",
         xi.len(),
         eki.msk.len()
     );
-    let p = types::DVec::from(tools::double_hash_to_curve_in_g1(label.as_ref()));
-    let r1 = tools::mat_mul(&eki.s, &p)?;
+    let p = types::DVec::from(tools::double_hash_to_curve_in_g1(label.canonical_bytes()));
+    let mut r1 = tools::mat_mul(&eki.s, &p)?;
     let ci = xi
         .iter()
         .zip(r1.iter())
         .map(|(xij, r)| tools::smul_in_g1(xij) + r);
 
     // add an IPFE layer to secure the multiple contributions
-    let u_l = tools::hash_to_curve(label.as_ref());
+    let u_l = tools::hash_to_curve(label.canonical_bytes());
     let r2 = eki.msk.iter().map(|&ipfe::PrivateKey(mski)| u_l * mski);
-    Ok(ci.zip(r2).map(|(cij, r)| CypherText(r + cij)).collect())
+    let res = ci.zip(r2).map(|(cij, r)| CypherText(r + cij)).collect();
+
+    // wipe the masks
+    tools::wipe(&mut r1);
+    Ok(res)
 }
 
 /// Compute the decryption key for a given vector `y`.
@@ -84,11 +221,19 @@ pub fn dkey_gen(msk: &[PrivateKey], y: &[Vec<Scalar>]) -> Result<DecryptionKey>
     let mut d = types::DVec::new(Scalar::zero(), Scalar::zero());
     let mut ip_dk = Vec::with_capacity(y.len());
     for (ski, yi) in msk.iter().zip(y.iter()) {
+        ski.validate()?;
         ip_dk.push(ipfe::key_gen(&ski.msk, yi)?);
-        d += &types::DVec::try_from(
-            tools::scal_mat_mul_dim_2(&tools::transpose(&ski.s)?, yi)?.as_slice(),
-        )
-        .map_err(|_| eyre::eyre!("Cannot convert the given dki into a DVec!"))?;
+        let mut st = tools::transpose(&ski.s)?;
+        let mut dky_i = tools::scal_mat_mul_dim_2(&st, yi)?;
+        let res = types::DVec::try_from(dky_i.as_slice())
+            .map_err(|_| eyre::eyre!("Cannot convert the given dki into a DVec!"));
+
+        // wipe the copies of the secret key and the secret-derived terms
+        st.iter_mut().for_each(|row| tools::wipe(row));
+        tools::wipe(&mut dky_i);
+        let mut res = res?;
+        d += &res;
+        tools::wipe(&mut res[..]);
     }
     Ok(DecryptionKey {
         y: y.to_vec(),
@@ -119,7 +264,7 @@ pub fn decrypt(
         .map(|((ci, yi), ip_dki)| {
             ipfe::decrypt(
                 &ipfe::CypherText {
-                    c0: tools::hash_to_curve(label.as_ref()),
+                    c0: tools::hash_to_curve(label.canonical_bytes()),
                     cx: ci.iter().collect(),
                 },
                 yi,
@@ -128,6 +273,45 @@ pub fn decrypt(
         })
         .sum();
 
-    let u = types::DVec::from(tools::double_hash_to_curve_in_g1(label.as_ref()));
+    let u = types::DVec::from(tools::double_hash_to_curve_in_g1(label.canonical_bytes()));
     Ok(d_l - u.inner_product(&dk_y.d))
 }
+
+/// Run the complete MCFE in a single process: generate the keys of the `n`
+/// clients, encrypt their `m` contributions, compute the decryption key and
+/// decrypt. Return `<x,y>.g1`.
+/// - `x`   : `(n, m)` contribution matrix
+/// - `y`   : `(n, m)` decryption function
+/// - `l`   : label
+/// - `rng` : random number generator
+///
+/// ```
+/// use cosmian_bls12_381::{G1Projective, Scalar};
+/// use dmcfe::{ipmcfe, types::Label};
+///
+/// let x = vec![vec![Scalar::from(1), Scalar::from(2)]; 3];
+/// let y = vec![vec![Scalar::from(3), Scalar::from(4)]; 3];
+/// let res = ipmcfe::run(&x, &y, &Label::from("label"), &mut rand::thread_rng()).unwrap();
+/// assert_eq!(res, G1Projective::generator() * Scalar::from(33));
+/// ```
+pub fn run<R: CryptoRng + RngCore>(
+    x: &[Vec<Scalar>],
+    y: &[Vec<Scalar>],
+    l: &types::Label,
+    rng: &mut R,
+) -> Result<G1Projective> {
+    eyre::ensure!(x.len() == y.len(), "x and y should have the same size!");
+    eyre::ensure!(!x.is_empty(), "The given text vector should not be empty!");
+    let m = x[0].len();
+    eyre::ensure!(
+        x.iter().chain(y.iter()).all(|v| v.len() == m),
+        "All contributions and function rows should have the same size!"
+    );
+    let msk = (0..x.len()).map(|_| setup(m, rng)).collect::<Vec<_>>();
+    let c = x
+        .iter()
+        .zip(msk.iter())
+        .map(|(xi, eki)| encrypt(eki, xi, l))
+        .collect::<Result<Vec<_>>>()?;
+    decrypt(&c, &dkey_gen(&msk, y)?, l)
+}
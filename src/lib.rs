@@ -1,5 +1,4 @@
 // Private modules
-mod tools;
 mod notes {
     mod dlp;
 }
@@ -9,4 +8,49 @@ pub mod dsum;
 pub mod ipdmcfe;
 pub mod ipfe;
 pub mod ipmcfe;
+#[cfg(any(test, feature = "testkit"))]
+pub mod testkit;
+pub mod tools;
 pub mod types;
+
+/// Version of the wire formats and protocol conventions (label format, scheme
+/// dimensions, serializations). Bump it whenever a change affects the data
+/// exchanged between peers.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Check the protocol version announced by a peer is compatible with ours.
+/// Peers using different versions would silently produce wrong results.
+/// - `peer`: protocol version of the peer
+pub fn check_version(peer: u32) -> eyre::Result<()> {
+    eyre::ensure!(
+        peer == PROTOCOL_VERSION,
+        "Incompatible protocol versions: peer uses {} instead of {}!",
+        peer,
+        PROTOCOL_VERSION
+    );
+    Ok(())
+}
+
+/// Summary of the scheme parameters, e.g. to include in bug reports:
+/// - `curve`:            pairing-friendly curve
+/// - `dimension`:        dimension of the DMCFE vectors
+/// - `hash`:             hash function used to hash labels and functions to
+///   the curve
+/// - `protocol_version`: version of the wire formats
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SchemeInfo {
+    pub curve: &'static str,
+    pub dimension: usize,
+    pub hash: &'static str,
+    pub protocol_version: u32,
+}
+
+/// Return the summary of the scheme parameters used by this crate.
+pub fn scheme_info() -> SchemeInfo {
+    SchemeInfo {
+        curve: "BLS12-381",
+        dimension: types::DIM,
+        hash: "SHA-256",
+        protocol_version: PROTOCOL_VERSION,
+    }
+}
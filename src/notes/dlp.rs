@@ -47,4 +47,4 @@
 //!
 //! The DLP was solved for a 114-bit long order in 6 months with 2000 CPU cores [^1]. Since our algorithm aims at being used for instantaneous encryption/decryption, a lower upper bound should be chosen. The following table gives a survey of the time and space efficiency of our algorithm given the size of the upper bound.
 //!
-//! [^1] Solving 114-bit ECDLP for a Barreto-Naehrig curve: <https://hal.archives-ouvertes.fr/hal-01633653/file/article.pdf>
+//! [^1]: Solving 114-bit ECDLP for a Barreto-Naehrig curve: <https://hal.archives-ouvertes.fr/hal-01633653/file/article.pdf>
@@ -0,0 +1,218 @@
+//! Testing and audit tools.
+//!
+//! These tools are meant for testing and compliance audits only: they must
+//! never be used to generate production keys. They are only compiled with the
+//! `testkit` feature.
+
+use crate::{ipfe, ipmcfe, tools};
+use cosmian_bls12_381::{G1Projective, Scalar};
+use eyre::Result;
+use rand_core::{CryptoRng, Error, RngCore};
+use sha2::{Digest, Sha256};
+
+/// Random number generator wrapper recording every byte drawn from the
+/// wrapped generator, so that they can be replayed using a `ReplayRng`.
+pub struct RecordingRng<R> {
+    /// - `rng`:    wrapped random number generator
+    rng: R,
+    /// - `record`: bytes drawn so far
+    record: Vec<u8>,
+}
+
+impl<R: CryptoRng + RngCore> RecordingRng<R> {
+    /// Wrap the given random number generator.
+    /// - `rng` : random number generator
+    pub fn new(rng: R) -> Self {
+        Self {
+            rng,
+            record: Vec::new(),
+        }
+    }
+
+    /// Return the bytes drawn so far.
+    pub fn record(&self) -> &[u8] {
+        &self.record
+    }
+
+    /// Return the bytes drawn so far, dropping the wrapped generator.
+    pub fn into_record(self) -> Vec<u8> {
+        self.record
+    }
+}
+
+impl<R: CryptoRng + RngCore> RngCore for RecordingRng<R> {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest);
+        self.record.extend_from_slice(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.rng.try_fill_bytes(dest)?;
+        self.record.extend_from_slice(dest);
+        Ok(())
+    }
+}
+
+impl<R: CryptoRng + RngCore> CryptoRng for RecordingRng<R> {}
+
+/// Random number generator replaying bytes recorded by a `RecordingRng`.
+///
+/// It is only marked as `CryptoRng` in order to be accepted by the setup
+/// functions: its output is fully known and it is not secure.
+pub struct ReplayRng {
+    /// - `record`: bytes to replay
+    record: Vec<u8>,
+    /// - `pos`:    number of bytes already replayed
+    pos: usize,
+}
+
+impl ReplayRng {
+    /// Create a generator replaying the given bytes.
+    /// - `record`  : bytes to replay
+    pub fn new(record: Vec<u8>) -> Self {
+        Self { record, pos: 0 }
+    }
+
+    /// Return the number of bytes left to replay.
+    pub fn remaining(&self) -> usize {
+        self.record.len() - self.pos
+    }
+}
+
+impl RngCore for ReplayRng {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    /// Panic if not enough bytes are left to replay.
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        assert!(
+            dest.len() <= self.remaining(),
+            "Not enough bytes left to replay: {} instead of {}!",
+            self.remaining(),
+            dest.len()
+        );
+        dest.copy_from_slice(&self.record[self.pos..self.pos + dest.len()]);
+        self.pos += dest.len();
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for ReplayRng {}
+
+/// Deterministic random number generator derived from a master seed and a
+/// stream ID, e.g. a thread or client ID: a multithreaded test run can be
+/// replayed exactly from its master seed, each thread using its own stream.
+/// The output is `H(H(seed | stream) | counter)` for successive counters.
+///
+/// It is only marked as `CryptoRng` in order to be accepted by the setup
+/// functions: its output is fully determined by the seed and it is not secure.
+pub struct SeededRng {
+    /// - `key`:    stream key `H(seed | stream)`
+    key: [u8; 32],
+    /// - `counter`: number of blocks generated so far
+    counter: u64,
+    /// - `block`:  current output block
+    block: [u8; 32],
+    /// - `pos`:    number of bytes of the current block already used
+    pos: usize,
+}
+
+impl SeededRng {
+    /// Create the generator of the given stream.
+    /// - `seed`    : master seed
+    /// - `stream`  : stream ID
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(b"SeededRng");
+        hasher.update(seed.to_le_bytes());
+        hasher.update(stream.to_le_bytes());
+        let mut key = [0; 32];
+        key.copy_from_slice(&hasher.finalize());
+        Self {
+            key,
+            counter: 0,
+            block: [0; 32],
+            pos: 32,
+        }
+    }
+}
+
+impl RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest.iter_mut() {
+            if self.pos == self.block.len() {
+                let mut hasher = Sha256::new();
+                hasher.update(self.key);
+                hasher.update(self.counter.to_le_bytes());
+                self.block.copy_from_slice(&hasher.finalize());
+                self.counter += 1;
+                self.pos = 0;
+            }
+            *byte = self.block[self.pos];
+            self.pos += 1;
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for SeededRng {}
+
+/// Check the IPFE scheme recovers the inner product of known vectors in
+/// dimension `m`, independently of the MCFE masking layer: once with keys
+/// given by `ipfe::setup`, and once with the IPFE keys embedded by
+/// `ipmcfe::setup`. This isolates whether a bug lies in the IPFE scheme or in
+/// the MCFE wrapper.
+/// - `m`   : dimension of the vectors
+/// - `rng` : random number generator
+pub fn check_ipfe_consistency<R: CryptoRng + RngCore>(m: usize, rng: &mut R) -> Result<()> {
+    eyre::ensure!(m > 0, "The dimension cannot be zero!");
+    let x = (1..=m as u64).map(Scalar::from).collect::<Vec<_>>();
+    let y = (1..=m as u64).rev().map(Scalar::from).collect::<Vec<_>>();
+    let expected = G1Projective::generator() * tools::scalar_dot(&x, &y)?;
+
+    let (msk, _) = ipfe::setup(m, rng);
+    let mcfe_msk = ipmcfe::setup(m, rng).msk;
+    for (msk, origin) in [(msk, "ipfe::setup"), (mcfe_msk, "ipmcfe::setup")] {
+        let mpk = msk
+            .iter()
+            .map(|si| ipfe::PublicKey(tools::smul_in_g1(si)))
+            .collect::<Vec<_>>();
+        let c = ipfe::encrypt(&mpk, &x, rng)?;
+        let sky = ipfe::key_gen(&msk, &y)?;
+        eyre::ensure!(
+            ipfe::decrypt(&c, &y, &sky) == expected,
+            "IPFE does not recover the inner product with the keys given by {}!",
+            origin
+        );
+    }
+    Ok(())
+}
@@ -1,14 +1,125 @@
 use cosmian_bls12_381::{
     hash_to_curve::{ExpandMsgXmd, HashToCurve},
-    G1Affine, G1Projective, G2Projective, Scalar,
+    G1Affine, G1Projective, G2Affine, G2Projective, Gt, Scalar,
 };
 use eyre::Result;
 use rand_core::{CryptoRng, RngCore};
 use sha2::{Digest, Sha256, Sha512};
 use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::ops::{Add, Deref};
+use std::sync::OnceLock;
 
 const DST: &[u8] = b"simple_DST";
 
+/// Build a scalar from a `u64`. `Scalar::from_raw` takes the limbs from the
+/// least significant to the most significant one: `x` is placed in the least
+/// significant limb, i.e. the result is the integer `x` itself.
+/// - `x`   : integer value
+#[inline]
+pub fn scalar_from_u64_le(x: u64) -> Scalar {
+    Scalar::from_raw([x, 0, 0, 0])
+}
+
+/// Convert back a scalar to a `u64`. This is the inverse of
+/// `scalar_from_u64_le`: fail if the scalar does not fit in the least
+/// significant limb.
+/// - `s`   : scalar
+pub fn scalar_to_u64(s: &Scalar) -> Result<u64> {
+    let bytes = s.to_bytes();
+    eyre::ensure!(
+        bytes[8..].iter().all(|&b| b == 0),
+        "The scalar does not fit in a u64!"
+    );
+    let mut res = [0; 8];
+    res.copy_from_slice(&bytes[..8]);
+    Ok(u64::from_le_bytes(res))
+}
+
+/// Build a scalar from a `u128`: the low 64 bits of `x` are placed in the
+/// least significant limb and the high 64 bits in the next one.
+/// - `x`   : integer value
+#[inline]
+pub fn scalar_from_u128(x: u128) -> Scalar {
+    Scalar::from_raw([x as u64, (x >> 64) as u64, 0, 0])
+}
+
+/// Convert back a scalar to a `u128`. This is the inverse of
+/// `scalar_from_u128`: fail if the scalar does not fit in the two least
+/// significant limbs.
+/// - `s`   : scalar
+pub fn scalar_to_u128(s: &Scalar) -> Result<u128> {
+    let bytes = s.to_bytes();
+    eyre::ensure!(
+        bytes[16..].iter().all(|&b| b == 0),
+        "The scalar does not fit in a u128!"
+    );
+    let mut res = [0; 16];
+    res.copy_from_slice(&bytes[..16]);
+    Ok(u128::from_le_bytes(res))
+}
+
+/// Compute `x.base` in Gt, which is written additively: this is the reference
+/// point `<x,y>.gT` a DMCFE decryption is compared against when `base` is
+/// `e(g1, g2)`.
+/// - `base`: Gt element
+/// - `x`   : integer
+pub fn gt_pow(base: &Gt, x: u64) -> Gt {
+    base * scalar_from_u64_le(x)
+}
+
+/// Return how many more labels can be aggregated before the running total
+/// may exceed the range in which the result can be recovered, i.e. the
+/// largest `k` such that `(labels_so_far + k) * per_label_bound <=
+/// dlp_bound`. Zero means the aggregation should be reset before the next
+/// label. A zero `per_label_bound` never exhausts the range: `u64::MAX` is
+/// returned.
+/// - `labels_so_far`   : number of labels already aggregated
+/// - `per_label_bound` : bound on the aggregated value of one label
+/// - `dlp_bound`       : largest recoverable total
+pub fn aggregation_headroom(labels_so_far: u64, per_label_bound: u64, dlp_bound: u64) -> u64 {
+    if per_label_bound == 0 {
+        return u64::MAX;
+    }
+    (dlp_bound / per_label_bound).saturating_sub(labels_so_far)
+}
+
+/// Compute the inner product of two scalar vectors: `Sum(xi.yi)`.
+/// - `x`   : scalar vector
+/// - `y`   : scalar vector
+pub fn scalar_dot(x: &[Scalar], y: &[Scalar]) -> Result<Scalar> {
+    eyre::ensure!(
+        x.len() == y.len(),
+        "Lengths do not match: {} and {}!",
+        x.len(),
+        y.len()
+    );
+    Ok(x.iter().zip(y.iter()).map(|(xi, yi)| xi * yi).sum())
+}
+
+/// Compute the inner product of two scalar matrices: `Sum(xij.yij)`.
+/// - `x`   : scalar matrix
+/// - `y`   : scalar matrix
+pub fn scalar_dot_2d(x: &[Vec<Scalar>], y: &[Vec<Scalar>]) -> Result<Scalar> {
+    eyre::ensure!(
+        x.len() == y.len(),
+        "Numbers of rows do not match: {} and {}!",
+        x.len(),
+        y.len()
+    );
+    x.iter()
+        .zip(y.iter())
+        .map(|(xi, yi)| scalar_dot(xi, yi))
+        .sum()
+}
+
+/// Build a scalar from fuzzer bytes.
+/// - `u`   : fuzzer bytes
+#[cfg(feature = "arbitrary")]
+pub(crate) fn arbitrary_scalar(u: &mut arbitrary::Unstructured) -> arbitrary::Result<Scalar> {
+    Ok(Scalar::from_bytes_wide(&u.arbitrary()?))
+}
+
 /// Draw a random scalar from Fp.
 ///
 /// - `rng` : random number generator
@@ -32,12 +143,102 @@ pub(crate) fn smul_in_g2(a: &Scalar) -> G2Projective {
     G2Projective::generator() * a
 }
 
+/// Window size, in bits, of the fixed-base multiplication tables
+const WINDOW: usize = 4;
+
+/// Precomputed fixed-base multiplication table: the row `i` holds the points
+/// `k.2^(WINDOW.i).g` for `k` in `0..2^WINDOW`.
+type WindowTable<T> = Vec<[T; 1 << WINDOW]>;
+
+/// Compute the fixed-base multiplication table of the given generator.
+/// - `g`       : generator
+/// - `identity`: group identity
+fn window_table<T: Copy + Add<Output = T>>(g: T, identity: T) -> WindowTable<T> {
+    let mut base = g;
+    (0..256 / WINDOW)
+        .map(|_| {
+            let mut row = [identity; 1 << WINDOW];
+            for k in 1..row.len() {
+                row[k] = row[k - 1] + base;
+            }
+            base = row[row.len() - 1] + base;
+            row
+        })
+        .collect()
+}
+
+/// Multiply the generator of the given table by the given scalar.
+/// - `table`   : fixed-base multiplication table
+/// - `identity`: group identity
+/// - `a`       : scalar
+fn window_mul<T: Copy + Add<Output = T>>(table: &[[T; 1 << WINDOW]], identity: T, a: &Scalar) -> T {
+    a.to_bytes()
+        .iter()
+        .flat_map(|b| [b & 0x0f, b >> 4])
+        .zip(table.iter())
+        .fold(identity, |acc, (w, row)| acc + row[w as usize])
+}
+
+/// Multiply the G1 generator by the given scalar using a precomputed table,
+/// which is faster than `G1Projective::generator() * a`. The table is
+/// computed on the first call.
+///
+/// This is not constant time: only use it with public scalars.
+///
+/// - `a`: scalar
+pub fn gen_mul_g1(a: &Scalar) -> G1Projective {
+    static TABLE: OnceLock<WindowTable<G1Projective>> = OnceLock::new();
+    let table =
+        TABLE.get_or_init(|| window_table(G1Projective::generator(), G1Projective::identity()));
+    window_mul(table, G1Projective::identity(), a)
+}
+
+/// Multiply the G2 generator by the given scalar using a precomputed table,
+/// which is faster than `G2Projective::generator() * a`. The table is
+/// computed on the first call.
+///
+/// This is not constant time: only use it with public scalars.
+///
+/// - `a`: scalar
+pub fn gen_mul_g2(a: &Scalar) -> G2Projective {
+    static TABLE: OnceLock<WindowTable<G2Projective>> = OnceLock::new();
+    let table =
+        TABLE.get_or_init(|| window_table(G2Projective::generator(), G2Projective::identity()));
+    window_mul(table, G2Projective::identity(), a)
+}
+
 /// Returns the hash of the given bytestring in `G1`
 /// - `m`: given `usize`
 pub(crate) fn hash_to_curve(m: &[u8]) -> G1Projective {
     <G1Projective as HashToCurve<ExpandMsgXmd<sha2::Sha256>>>::hash_to_curve(m, DST)
 }
 
+/// Decompress a G1 point. The subgroup check is skipped if `checked` is
+/// `false`: only do so for trusted inputs.
+/// - `bytes`   : compressed point
+/// - `checked` : check that the point is in the prime-order subgroup
+pub(crate) fn g1_from_compressed(bytes: &[u8; 48], checked: bool) -> Option<G1Projective> {
+    let p = if checked {
+        G1Affine::from_compressed(bytes)
+    } else {
+        G1Affine::from_compressed_unchecked(bytes)
+    };
+    Option::<G1Affine>::from(p).map(G1Projective::from)
+}
+
+/// Decompress a G2 point. The subgroup check is skipped if `checked` is
+/// `false`: only do so for trusted inputs.
+/// - `bytes`   : compressed point
+/// - `checked` : check that the point is in the prime-order subgroup
+pub(crate) fn g2_from_compressed(bytes: &[u8; 96], checked: bool) -> Option<G2Projective> {
+    let p = if checked {
+        G2Affine::from_compressed(bytes)
+    } else {
+        G2Affine::from_compressed_unchecked(bytes)
+    };
+    Option::<G2Affine>::from(p).map(G2Projective::from)
+}
+
 /// Returns the hash of the given bytestring in `G1xG1`
 /// - `m`: given `usize`
 pub(crate) fn double_hash_to_curve_in_g1(m: &[u8]) -> (G1Projective, G1Projective) {
@@ -72,6 +273,123 @@ pub(crate) fn hash_to_scalar(
     Scalar::from_bytes_wide(&m)
 }
 
+/// Serialization format header, shared by all the serialized types.
+pub mod codec {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use eyre::Result;
+
+    /// Current version of the serialization formats. Every serialized type is
+    /// prefixed by this 1-byte version, which is bumped whenever an encoding
+    /// changes. Deserializers reject any other version.
+    pub const FORMAT_VERSION: u8 = 1;
+
+    /// Size of the format header, in bytes
+    pub const HEADER_LEN: usize = 1;
+
+    /// Return a new buffer holding the format header.
+    /// - `capacity`: expected size of the serialized data, header excluded
+    pub(crate) fn new_buffer(capacity: usize) -> Vec<u8> {
+        let mut res = Vec::with_capacity(HEADER_LEN + capacity);
+        res.push(FORMAT_VERSION);
+        res
+    }
+
+    /// Read the format header from the head of the given bytes, and move the
+    /// head forward. Fail if the format version is unknown.
+    /// - `bytes`   : bytes to read
+    pub(crate) fn read_header(bytes: &mut &[u8]) -> Result<()> {
+        let (&version, tail) = bytes
+            .split_first()
+            .ok_or_else(|| eyre::eyre!("Cannot read the format header: no bytes left!"))?;
+        eyre::ensure!(
+            version == FORMAT_VERSION,
+            "Unknown format version {}: only version {} is supported!",
+            version,
+            FORMAT_VERSION
+        );
+        *bytes = tail;
+        Ok(())
+    }
+
+    /// Wrap the given bytes in a PEM block with the given label, as base64
+    /// lines of 64 characters.
+    /// - `label`   : PEM label, e.g. `DMCFE PRIVATE KEY`
+    /// - `bytes`   : serialized data
+    pub(crate) fn to_pem(label: &str, bytes: &[u8]) -> String {
+        let b64 = STANDARD.encode(bytes);
+        let mut res = format!("-----BEGIN {}-----\n", label);
+        for line in b64.as_bytes().chunks(64) {
+            res.push_str(&String::from_utf8_lossy(line));
+            res.push('\n');
+        }
+        res.push_str(&format!("-----END {}-----\n", label));
+        res
+    }
+
+    /// Read the bytes wrapped in a PEM block. Fail if the PEM label is not the
+    /// given one.
+    /// - `label`   : expected PEM label
+    /// - `pem`     : PEM block
+    pub(crate) fn from_pem(label: &str, pem: &str) -> Result<Vec<u8>> {
+        let body = pem
+            .trim()
+            .strip_prefix(&format!("-----BEGIN {}-----", label))
+            .and_then(|body| body.strip_suffix(&format!("-----END {}-----", label)))
+            .ok_or_else(|| eyre::eyre!("Not a PEM block labelled {}!", label))?;
+        let b64 = body.split_whitespace().collect::<String>();
+        STANDARD
+            .decode(b64)
+            .map_err(|err| eyre::eyre!("Invalid PEM encoding: {}!", err))
+    }
+}
+
+/// Read a little-endian `u64` from the head of the given bytes, and move the
+/// head forward.
+/// - `bytes`   : bytes to read
+pub(crate) fn read_u64(bytes: &mut &[u8]) -> Result<u64> {
+    eyre::ensure!(
+        bytes.len() >= 8,
+        "Cannot read a u64: only {} bytes left!",
+        bytes.len()
+    );
+    let (head, tail) = bytes.split_at(8);
+    *bytes = tail;
+    let mut res = [0; 8];
+    res.copy_from_slice(head);
+    Ok(u64::from_le_bytes(res))
+}
+
+/// Read a length header from the head of the given bytes, and move the head
+/// forward. Check enough bytes are left for the announced number of elements.
+/// - `bytes`       : bytes to read
+/// - `elt_size`    : size of the elements in bytes
+pub(crate) fn read_len(bytes: &mut &[u8], elt_size: usize) -> Result<usize> {
+    let len = usize::try_from(read_u64(bytes)?)?;
+    eyre::ensure!(
+        len.checked_mul(elt_size)
+            .is_some_and(|size| size <= bytes.len()),
+        "Announced length {} exceeds the {} bytes left!",
+        len,
+        bytes.len()
+    );
+    Ok(len)
+}
+
+/// Read a scalar from the head of the given bytes, and move the head forward.
+/// - `bytes`   : bytes to read
+pub(crate) fn read_scalar(bytes: &mut &[u8]) -> Result<Scalar> {
+    eyre::ensure!(
+        bytes.len() >= 32,
+        "Cannot read a scalar: only {} bytes left!",
+        bytes.len()
+    );
+    let (head, tail) = bytes.split_at(32);
+    *bytes = tail;
+    let mut res = [0; 32];
+    res.copy_from_slice(head);
+    Option::from(Scalar::from_bytes(&res)).ok_or_else(|| eyre::eyre!("Invalid scalar encoding!"))
+}
+
 /// generate a random `(m,n)` matrix of `Fp` elements.
 /// - `m`   :  matrix size 1;
 /// - `n`   :  matrix size 2.
@@ -86,39 +404,81 @@ pub(crate) fn random_mat_gen<R: RngCore + CryptoRng>(
         .collect()
 }
 
-/// Transpose the given matrix,
+/// Transpose the given matrix. Fail if the matrix is empty or ragged, i.e. if
+/// its rows do not all have the same length.
 /// - `v`: matrix to transpose
-pub(crate) fn transpose<T: Copy>(v: &[Vec<T>]) -> Result<Vec<Vec<T>>> {
-    eyre::ensure!(!v.is_empty(), "0 has no inverse!");
+pub fn transpose<T: Copy>(v: &[Vec<T>]) -> Result<Vec<Vec<T>>> {
+    eyre::ensure!(!v.is_empty(), "Cannot transpose an empty matrix!");
     let len = v[0].len();
-    let mut iters = v.iter().map(|n| n.iter()).collect::<Vec<_>>();
+    if let Some((i, row)) = v.iter().enumerate().find(|(_, row)| row.len() != len) {
+        eyre::bail!(
+            "Ragged matrix: row {} has {} elements instead of {}!",
+            i,
+            row.len(),
+            len
+        );
+    }
     Ok((0..len)
-        .map(|_| {
-            iters
-                .iter_mut()
-                .map(|n| *n.next().expect("Error while accessing elements of v!"))
-                .collect::<Vec<T>>()
-        })
+        .map(|j| v.iter().map(|row| row[j]).collect::<Vec<T>>())
         .collect())
 }
 
+/// Overwrite the given elements with their default value (zero or identity),
+/// in a way the compiler cannot optimize out since the buffer is about to be
+/// dropped. Use it to wipe temporaries holding secret-derived values.
+/// - `v`   : elements to wipe
+pub(crate) fn wipe<T: Copy + Default>(v: &mut [T]) {
+    for e in v.iter_mut() {
+        // SAFETY: `e` is a valid and aligned mutable reference
+        unsafe { std::ptr::write_volatile(e, T::default()) };
+    }
+    std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
+}
+
+/// Secret-derived value wiped using `wipe` when dropped, for values which
+/// outlive the function computing them (e.g. captured by an iterator). This
+/// mirrors `zeroize::Zeroizing`, which requires the curve types to implement
+/// `Zeroize`.
+pub(crate) struct Wiped<T: Copy + Default>(pub(crate) T);
+
+impl<T: Copy + Default> Deref for Wiped<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T: Copy + Default> Drop for Wiped<T> {
+    fn drop(&mut self) {
+        wipe(std::slice::from_mut(&mut self.0));
+    }
+}
+
 /// Compute the matrix/vector multiplication in `G1`: `x.y`, where `x` is a
 /// scalar matrix and `y` a matrix of G1 elements.
+///
+/// The error reports the shapes of both operands when they are incompatible.
 /// - `x`: matrix
 /// - `y`: vector
-pub(crate) fn mat_mul(x: &[Vec<Scalar>], y: &[G1Projective]) -> Result<Vec<G1Projective>> {
+pub fn mat_mul(x: &[Vec<Scalar>], y: &[G1Projective]) -> Result<Vec<G1Projective>> {
+    let cols = x
+        .first()
+        .ok_or_else(|| eyre::eyre!("Empty matrices are not allowed!"))?
+        .len();
+    if let Some((i, row)) = x.iter().enumerate().find(|(_, row)| row.len() != cols) {
+        eyre::bail!(
+            "Ragged left matrix: row {} has {} columns instead of {}!",
+            i,
+            row.len(),
+            cols
+        );
+    }
     eyre::ensure!(
-        !x.is_empty()
-            && x.first()
-                .ok_or_else(|| eyre::eyre!("Error while accessing the first element of x!"))?
-                .len()
-                == y.len(),
-        "Lengths do not match!\n
-        x ({}, {}) vs y ({}, 1)",
+        cols == y.len(),
+        "Incompatible shapes: x is ({}, {}) but y is ({}, 1)!",
         x.len(),
-        x.first()
-            .ok_or_else(|| eyre::eyre!("Empty matrices are not allowed"))?
-            .len(),
+        cols,
         y.len(),
     );
     Ok(x.iter()
@@ -1,5 +1,5 @@
-use crate::tools;
-use cosmian_bls12_381::{G1Projective, Scalar};
+use crate::{dsum, tools};
+use cosmian_bls12_381::{G1Affine, G1Projective, G2Affine, G2Projective, Scalar};
 use eyre::Result;
 use std::convert::TryFrom;
 use std::ops::AddAssign;
@@ -62,6 +62,42 @@ impl<T> TMat<T> {
     }
 }
 
+impl TMat<dsum::CypherText> {
+    /// Size of a serialized matrix: format header and four scalars, the
+    /// entries being serialized without their own header
+    pub const SERIALIZED_LEN: usize = tools::codec::HEADER_LEN
+        + 4 * (dsum::CypherText::SERIALIZED_LEN - tools::codec::HEADER_LEN);
+
+    /// Serialize the matrix in row-major order: the entries `a | b | c | d` of
+    /// `TMat::new(a, b, c, d)` are serialized as four 32-byte scalars, after
+    /// the format header.
+    pub fn to_bytes(&self) -> [u8; Self::SERIALIZED_LEN] {
+        let mut res = [0; Self::SERIALIZED_LEN];
+        res[0] = tools::codec::FORMAT_VERSION;
+        for (chunk, tij) in res[1..].chunks_exact_mut(32).zip(self.iter().flatten()) {
+            chunk.copy_from_slice(&tij.0.to_bytes());
+        }
+        res
+    }
+
+    /// Deserialize a matrix serialized using `to_bytes`.
+    /// - `bytes`   : serialized matrix
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        eyre::ensure!(
+            bytes.len() == Self::SERIALIZED_LEN,
+            "Wrong matrix size: {} bytes instead of {}!",
+            bytes.len(),
+            Self::SERIALIZED_LEN
+        );
+        let bytes = &mut bytes;
+        tools::codec::read_header(bytes)?;
+        let t = (0..4)
+            .map(|_| tools::read_scalar(bytes).map(dsum::CypherText))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(t[0], t[1], t[2], t[3]))
+    }
+}
+
 impl TMat<Scalar> {
     /// Generate `T_i` such that `Sum(T_i) = 0`.
     /// l       : label
@@ -80,9 +116,15 @@ impl TMat<Scalar> {
     }
 }
 
+/// Dimension of the DMCFE vectors
+pub const DIM: usize = 2;
+
+/// Domain tag prefixed to the labels bound to an application
+const APP_DOMAIN_TAG: &[u8] = b"DMCFE-app\0";
+
 /// 2 dimensional vector
 #[derive(Clone, Default, PartialEq, Eq)]
-pub struct DVec<T>([T; 2]);
+pub struct DVec<T>([T; DIM]);
 
 impl<T> DVec<T> {
     pub fn new(a: T, b: T) -> Self {
@@ -114,6 +156,96 @@ impl<'t, 'u, T: Clone> DVec<T> {
     }
 }
 
+impl DVec<G1Projective> {
+    /// Serialize the vector as the format header followed by two compressed
+    /// G1 points.
+    pub fn to_compressed(&self) -> Vec<u8> {
+        let mut res = tools::codec::new_buffer(2 * 48);
+        self.iter()
+            .for_each(|p| res.extend_from_slice(&G1Affine::from(p).to_compressed()));
+        res
+    }
+
+    /// Deserialize a vector serialized using `to_compressed`. Fail if a point
+    /// is not in the prime-order subgroup.
+    /// - `bytes`   : compressed G1 points
+    pub fn from_compressed(bytes: &[u8]) -> Result<Self> {
+        Self::decode(bytes, true)
+    }
+
+    /// Deserialize a vector serialized using `to_compressed`, without
+    /// checking that the points are in the prime-order subgroup. Only use it
+    /// for trusted inputs.
+    /// - `bytes`   : compressed G1 points
+    pub fn from_compressed_unchecked(bytes: &[u8]) -> Result<Self> {
+        Self::decode(bytes, false)
+    }
+
+    fn decode(mut bytes: &[u8], checked: bool) -> Result<Self> {
+        tools::codec::read_header(&mut bytes)?;
+        eyre::ensure!(
+            bytes.len() == 2 * 48,
+            "Wrong size: {} bytes instead of {} for two G1 points!",
+            bytes.len(),
+            2 * 48
+        );
+        let points = bytes
+            .chunks_exact(48)
+            .map(|chunk| {
+                let chunk = <&[u8; 48]>::try_from(chunk)?;
+                tools::g1_from_compressed(chunk, checked)
+                    .ok_or_else(|| eyre::eyre!("Invalid G1 point encoding!"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(points[0], points[1]))
+    }
+}
+
+impl DVec<G2Projective> {
+    /// Serialize the vector as the format header followed by two compressed
+    /// G2 points.
+    pub fn to_compressed(&self) -> Vec<u8> {
+        let mut res = tools::codec::new_buffer(2 * 96);
+        self.iter()
+            .for_each(|p| res.extend_from_slice(&G2Affine::from(p).to_compressed()));
+        res
+    }
+
+    /// Deserialize a vector serialized using `to_compressed`. Fail if a point
+    /// is not in the prime-order subgroup.
+    /// - `bytes`   : compressed G2 points
+    pub fn from_compressed(bytes: &[u8]) -> Result<Self> {
+        Self::decode(bytes, true)
+    }
+
+    /// Deserialize a vector serialized using `to_compressed`, without
+    /// checking that the points are in the prime-order subgroup. Only use it
+    /// for trusted inputs.
+    /// - `bytes`   : compressed G2 points
+    pub fn from_compressed_unchecked(bytes: &[u8]) -> Result<Self> {
+        Self::decode(bytes, false)
+    }
+
+    fn decode(mut bytes: &[u8], checked: bool) -> Result<Self> {
+        tools::codec::read_header(&mut bytes)?;
+        eyre::ensure!(
+            bytes.len() == 2 * 96,
+            "Wrong size: {} bytes instead of {} for two G2 points!",
+            bytes.len(),
+            2 * 96
+        );
+        let points = bytes
+            .chunks_exact(96)
+            .map(|chunk| {
+                let chunk = <&[u8; 96]>::try_from(chunk)?;
+                tools::g2_from_compressed(chunk, checked)
+                    .ok_or_else(|| eyre::eyre!("Invalid G2 point encoding!"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(points[0], points[1]))
+    }
+}
+
 impl<T> Deref for DVec<T> {
     type Target = [T];
 
@@ -204,6 +336,30 @@ impl Label {
         )
     }
 
+    /// Create a label bound to the given application: a fixed domain tag and
+    /// the application ID, along with its length, are prefixed to the label
+    /// bytes. This prevents two applications sharing the same key material
+    /// from sharing a label space: identical labels give different cyphertext
+    /// bases in different applications. The domain tag also separates these
+    /// labels from the ones built without an application ID.
+    /// - `app_id`  : application ID
+    /// - `bytes`   : label bytes
+    pub fn for_app(app_id: &[u8], bytes: &[u8]) -> Self {
+        let mut res = Vec::with_capacity(APP_DOMAIN_TAG.len() + 8 + app_id.len() + bytes.len());
+        res.extend_from_slice(APP_DOMAIN_TAG);
+        res.extend_from_slice(&(app_id.len() as u64).to_le_bytes());
+        res.extend_from_slice(app_id);
+        res.extend_from_slice(bytes);
+        Self(res)
+    }
+
+    /// Return the bytes hashed to the curve by the encryption and decryption
+    /// algorithms. Two labels give the same cyphertext basis if and only if
+    /// their canonical bytes are equal, whatever constructor was used.
+    pub fn canonical_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
     /// Aggregate the given byte-vector to the `Label`.
     pub fn aggregate<T>(&mut self, r: T)
     where
@@ -235,3 +391,129 @@ impl Deref for Label {
         &self.0
     }
 }
+
+/// Sum of scalar products `Sum(yi.xi)` tracking an upper bound of its integer
+/// value. Scalar additions silently wrap modulo the group order: the bound
+/// allows detecting sums that may not be recovered as integers, e.g. because
+/// they exceed the range in which the discrete logarithm can be solved.
+#[derive(Clone, Copy, Debug)]
+pub struct BoundedScalarSum {
+    /// - `sum`:    scalar sum
+    sum: Scalar,
+    /// - `bound`:  upper bound of the integer value of the sum
+    bound: u128,
+    /// - `limit`:  maximum bound allowed
+    limit: u128,
+}
+
+impl BoundedScalarSum {
+    /// Create a new empty sum.
+    /// - `limit`   : maximum integer value allowed for the sum
+    pub fn new(limit: u128) -> Self {
+        Self {
+            sum: Scalar::zero(),
+            bound: 0,
+            limit,
+        }
+    }
+
+    /// Add the product `y.x` to the sum. Fail without modifying the sum if
+    /// one of the values exceeds its bound, or if the bound of the sum would
+    /// exceed the limit.
+    /// - `x`       : scalar
+    /// - `x_bound` : upper bound of the integer value of `x`
+    /// - `y`       : scalar
+    /// - `y_bound` : upper bound of the integer value of `y`
+    pub fn add_product(
+        &mut self,
+        x: &Scalar,
+        x_bound: u64,
+        y: &Scalar,
+        y_bound: u64,
+    ) -> Result<()> {
+        for (v, b) in [(x, x_bound), (y, y_bound)] {
+            eyre::ensure!(
+                tools::scalar_to_u64(v).is_ok_and(|v| v <= b),
+                "Value exceeds its bound {}!",
+                b
+            );
+        }
+        let bound = u128::from(x_bound)
+            .checked_mul(u128::from(y_bound))
+            .and_then(|p| p.checked_add(self.bound))
+            .filter(|&bound| bound <= self.limit)
+            .ok_or_else(|| eyre::eyre!("The sum may exceed its limit {}!", self.limit))?;
+        self.sum += y * x;
+        self.bound = bound;
+        Ok(())
+    }
+
+    /// Return the scalar sum.
+    pub fn sum(&self) -> Scalar {
+        self.sum
+    }
+
+    /// Return the upper bound of the integer value of the sum.
+    pub fn bound(&self) -> u128 {
+        self.bound
+    }
+}
+
+/// Value sent by a client, along with the ID of this client
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClientIndexed<T> {
+    /// - `id`:     client ID
+    id: usize,
+    /// - `value`:  value sent by the client
+    value: T,
+}
+
+impl<T> ClientIndexed<T> {
+    /// Bind a value to the ID of the client which sent it.
+    /// - `id`      : client ID
+    /// - `value`   : value sent by the client
+    pub fn new(id: usize, value: T) -> Self {
+        Self { id, value }
+    }
+
+    /// Return the client ID.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Return the value sent by the client.
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Return the value sent by the client, dropping its ID.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> From<(T, usize)> for ClientIndexed<T> {
+    fn from((value, id): (T, usize)) -> Self {
+        Self::new(id, value)
+    }
+}
+
+/// Iterate over the given client values in the order of the client IDs.
+/// Fail if the IDs are not exactly `0..n`, with `n` the number of values, so
+/// that each value is aligned with the coefficient of its client in the
+/// decryption function.
+/// - `values`  : values sent by the clients, in any order
+pub fn enumerate_clients<T>(
+    mut values: Vec<ClientIndexed<T>>,
+) -> Result<std::vec::IntoIter<ClientIndexed<T>>> {
+    values.sort_by_key(ClientIndexed::id);
+    for (expected_id, value) in values.iter().enumerate() {
+        eyre::ensure!(
+            value.id == expected_id,
+            "Client IDs are not contiguous: expected client {}, got client {}!",
+            expected_id,
+            value.id
+        );
+    }
+    Ok(values.into_iter())
+}
@@ -7,7 +7,7 @@ use std::thread;
 
 // Arbitrary size limite for the db
 // TODO: think about a better size limit or system to keep the size small
-const MAX_SIZE: usize = 2 ^ 16;
+const MAX_SIZE: usize = 1 << 16;
 
 // wrapper to ease the use of this module
 pub type BusTx<T> = mpsc::Sender<Packet<T>>;
@@ -79,7 +79,8 @@ impl<T: 'static + Clone + Send + Sync> Bus<T> {
         Bus::<T> { tx, bus }
     }
 
-    /// Close the given bus.
+    /// Close the given bus. The data still in the bus are dropped, and the
+    /// clients still waiting for data get an error instead of hanging.
     pub fn close(self) -> Result<()> {
         safe_send(&self.tx, Packet::SigTerm)?;
         self.bus
@@ -141,18 +142,16 @@ impl<T: Clone> BusQueues<T> {
         // send broadcasted data if it hasn't been received yet
         // remember the id of the clients who already have the data
         // remove the data if all other clients already have it
-        for (index, broadcast) in self.public.iter_mut().enumerate() {
-            if broadcast.id_list.get(&id).is_none() {
+        for broadcast in self.public.iter_mut() {
+            if !broadcast.id_list.contains(&id) {
                 // TODO: do not clone data for the last client
                 safe_send(&tx, Ok(Some(broadcast.data.clone())))?;
-                if broadcast.id_list.len() < self.n {
-                    broadcast.id_list.insert(id);
-                } else {
-                    // if all clients have received the data, remove it
-                    broadcast.id_list.remove(&index);
-                }
+                broadcast.id_list.insert(id);
             }
         }
+        // if all clients have received the data, remove it
+        let n = self.n;
+        self.public.retain(|broadcast| broadcast.id_list.len() < n);
 
         // send the end-of-communication signal
         safe_send(&tx, Ok(None))
@@ -412,4 +411,51 @@ mod test {
         // close the bus
         bus.close()
     }
+
+    #[test]
+    fn test_bus_shutdown() -> Result<()> {
+        let bus = super::Bus::<usize>::open(2);
+
+        // this client waits for data which will never be sent
+        let tx = bus.tx.clone();
+        let client = thread::spawn(move || super::wait_n(&tx, 1, 0));
+        thread::sleep(std::time::Duration::from_millis(10));
+
+        bus.close()?;
+        eyre::ensure!(
+            client.join().unwrap().is_err(),
+            "Closing the bus should unblock the waiting clients with an error!"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bus_queue_size() -> Result<()> {
+        let bus = super::Bus::<usize>::open(2);
+
+        // queue more data than a client fetches at once in the simulations
+        let m = 1_000;
+        for datum in 0..m {
+            super::unicast(&bus.tx, 1, datum)?;
+        }
+        let res = super::get(&bus.tx, 1)?;
+        eyre::ensure!(
+            res.len() == m,
+            "Data were dropped: {} received instead of {}!",
+            res.len(),
+            m
+        );
+
+        // broadcast data are removed once all clients fetched them
+        super::broadcast(&bus.tx, 0)?;
+        eyre::ensure!(
+            super::get(&bus.tx, 0)?.len() == 1 && super::get(&bus.tx, 1)?.len() == 1,
+            "Broadcast data should be sent to all clients!"
+        );
+        eyre::ensure!(
+            super::get(&bus.tx, 0)?.is_empty() && super::get(&bus.tx, 1)?.is_empty(),
+            "Broadcast data should be sent only once!"
+        );
+        bus.close()
+    }
 }
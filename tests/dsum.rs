@@ -3,7 +3,7 @@
 mod bus;
 
 use cosmian_bls12_381::Scalar;
-use dmcfe::{dsum, types::Label};
+use dmcfe::{dsum, testkit::ReplayRng, types::Label};
 use eyre::Result;
 use rand::{rngs::ThreadRng, Rng};
 use std::thread;
@@ -107,3 +107,106 @@ fn test_dsum() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_share_of_zero() -> Result<()> {
+    let mut rng = ThreadRng::default();
+    let n: usize = rng.gen_range(2..10);
+    let keys: Vec<dsum::KeyPair> = (0..n).map(|_| dsum::client_setup(&mut rng)).collect();
+    let pk: Vec<dsum::PublicKey> = keys.iter().map(|dsum::KeyPair(_, pki)| *pki).collect();
+    let label = Label::new();
+
+    let shares: Vec<Scalar> = keys
+        .iter()
+        .map(|dsum::KeyPair(ski, _)| dsum::share_of_zero(ski, &pk, &label))
+        .collect();
+    eyre::ensure!(
+        shares.iter().sum::<Scalar>() == Scalar::zero(),
+        "The shares should sum to zero!"
+    );
+    eyre::ensure!(
+        shares.iter().any(|share| *share != Scalar::zero()),
+        "The shares should not be trivial!"
+    );
+
+    // the share is the mask of the encoding
+    let x = Scalar::from(42);
+    let dsum::KeyPair(sk0, _) = &keys[0];
+    eyre::ensure!(
+        *dsum::encode(&x, sk0, &pk, &label) == x + shares[0],
+        "The share should be the mask of the encoding!"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_client_setup_rejects_zero_key() -> Result<()> {
+    // the first scalar drawn is zero
+    let mut bytes = vec![0; 64];
+    bytes.extend((0..64).map(|_| rand::random::<u8>()));
+    let mut rng = ReplayRng::new(bytes);
+    let dsum::KeyPair(ski, pki) = dsum::client_setup(&mut rng);
+    eyre::ensure!(
+        *ski != Scalar::zero() && !bool::from(pki.is_identity()),
+        "A zero private key should be drawn again!"
+    );
+    eyre::ensure!(
+        rng.remaining() == 0,
+        "The key should be drawn a second time!"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_combine_aligned() -> Result<()> {
+    let mut rng = ThreadRng::default();
+    let n = 4;
+    let keys: Vec<dsum::KeyPair> = (0..n).map(|_| dsum::client_setup(&mut rng)).collect();
+    let pk: Vec<dsum::PublicKey> = keys.iter().map(|dsum::KeyPair(_, pki)| *pki).collect();
+    let encode_round = |label: &Label, x: &[u64]| {
+        keys.iter()
+            .zip(x.iter())
+            .enumerate()
+            .map(|(id, (dsum::KeyPair(ski, _), &xi))| {
+                (dsum::encode(&Scalar::from(xi), ski, &pk, label), id)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // the order of the cyphertexts in a round does not matter
+    let round_0 = encode_round(&Label::from("round 0"), &[1, 2, 3, 4]);
+    let mut round_1 = encode_round(&Label::from("round 1"), &[5, 6, 7, 8]);
+    round_1.reverse();
+    let res = dsum::combine_aligned(&[round_0.clone(), round_1.clone()], n)?;
+    eyre::ensure!(
+        res == [Scalar::from(10), Scalar::from(26)],
+        "Wrong aggregation results!"
+    );
+
+    // a client is missing from the second round
+    let partial = round_1[1..].to_vec();
+    eyre::ensure!(
+        dsum::combine_aligned(&[round_0.clone(), partial.clone()], n).is_err(),
+        "Mismatched client sets should be rejected!"
+    );
+
+    // the same client is missing from all the rounds
+    let partial_0 = round_0
+        .iter()
+        .filter(|(_, id)| partial.iter().any(|(_, id_)| id == id_))
+        .copied()
+        .collect::<Vec<_>>();
+    eyre::ensure!(
+        dsum::combine_aligned(&[partial_0, partial], n).is_err(),
+        "Rounds missing the same client should be rejected!"
+    );
+
+    // a client contributed twice to the second round
+    let mut duplicate = round_1.clone();
+    duplicate[0].1 = duplicate[1].1;
+    eyre::ensure!(
+        dsum::combine_aligned(&[round_0, duplicate], n).is_err(),
+        "Duplicate client IDs should be rejected!"
+    );
+    Ok(())
+}
@@ -9,7 +9,7 @@
 
 #![allow(non_snake_case)]
 use cosmian_bls12_381::{G1Projective, Scalar};
-use dmcfe::{ipmcfe, types::Label};
+use dmcfe::{ipfe, ipmcfe, tools, types::Label};
 use eyre::Result;
 use rand::rngs::ThreadRng;
 use rand::Rng;
@@ -64,6 +64,7 @@ fn decrypt_simulation(
 /// - `x`:  the contribution vector
 /// - `y`:  the vector associated with the decryption function
 /// - `l`:  the label
+///
 /// It returns the result of the MCFE in G1.
 fn simulation(x: &[Vec<Scalar>], y: &[Vec<Scalar>], label: &Label) -> Result<G1Projective> {
     // Copy vectors to gain ownership
@@ -132,16 +133,7 @@ fn test_mcfe() -> Result<()> {
 
     // compute the solution `G * <x,y>`
     // stay in G1 to avoid computing the discrete log
-    let s: G1Projective = G1Projective::generator()
-        * x.iter()
-            .zip(y.iter())
-            .map(|(xi, yi)| {
-                xi.iter()
-                    .zip(yi.iter())
-                    .map(|(xij, yij)| xij * yij)
-                    .sum::<Scalar>()
-            })
-            .sum::<Scalar>();
+    let s: G1Projective = G1Projective::generator() * tools::scalar_dot_2d(&x, &y)?;
 
     let res = simulation(&x, &y, &label)?;
     // compare it with the solution computed with the MCFE algorithm
@@ -151,3 +143,93 @@ fn test_mcfe() -> Result<()> {
     );
     Ok(())
 }
+
+#[test]
+fn test_decryption_key_serialization() -> Result<()> {
+    let mut rng = ThreadRng::default();
+    let (n, m) = (3, 2);
+    let x = vec![vec![Scalar::from_raw([rand::random(); 4]); m]; n];
+    let y = vec![vec![Scalar::from_raw([rand::random(); 4]); m]; n];
+    let label = Label::new();
+    let msk: Vec<ipmcfe::PrivateKey> = (0..n).map(|_| ipmcfe::setup(m, &mut rng)).collect();
+    let c = x
+        .iter()
+        .zip(msk.iter())
+        .map(|(xi, eki)| ipmcfe::encrypt(eki, xi, &label))
+        .collect::<Result<Vec<_>>>()?;
+    let dk = ipmcfe::dkey_gen(&msk, &y)?;
+
+    // round-trip
+    let bytes = dk.to_bytes();
+    eyre::ensure!(
+        bytes.capacity() == bytes.len(),
+        "The serialization buffer was reallocated!"
+    );
+    let dk_ = ipmcfe::DecryptionKey::from_bytes(&bytes)?;
+    eyre::ensure!(dk_.to_bytes() == bytes, "Round-trip changed the key!");
+    eyre::ensure!(
+        ipmcfe::decrypt(&c, &dk_, &label)? == ipmcfe::decrypt(&c, &dk, &label)?,
+        "Deserialized key gives a different result!"
+    );
+
+    // malformed inputs
+    eyre::ensure!(
+        ipmcfe::DecryptionKey::from_bytes(&bytes[..bytes.len() - 1]).is_err(),
+        "Truncated key should be rejected!"
+    );
+    eyre::ensure!(
+        ipmcfe::DecryptionKey::from_bytes(&[bytes.as_slice(), &[0]].concat()).is_err(),
+        "Key with trailing bytes should be rejected!"
+    );
+    let mut wrong_dim = bytes.clone();
+    wrong_dim[1] += 1;
+    eyre::ensure!(
+        ipmcfe::DecryptionKey::from_bytes(&wrong_dim).is_err(),
+        "Key with inconsistent dimensions should be rejected!"
+    );
+
+    // the second row only holds one scalar: `header | n | m_0 | y_0 | m_1`
+    let row_1 = 1 + 8 + 8 + 32 * m;
+    let mut ragged = bytes[..row_1].to_vec();
+    ragged.extend_from_slice(&1u64.to_le_bytes());
+    ragged.extend_from_slice(&bytes[row_1 + 8 + 32..]);
+    eyre::ensure!(
+        ipmcfe::DecryptionKey::from_bytes(&ragged).is_err(),
+        "Key with ragged rows should be rejected!"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_encryption_key_validation() -> Result<()> {
+    let mut rng = ThreadRng::default();
+    let m = 3;
+    let eki = ipmcfe::setup(m, &mut rng);
+    eki.validate()?;
+
+    // round-trip
+    let bytes = eki.to_bytes();
+    let eki_ = ipmcfe::PrivateKey::from_bytes(&bytes)?;
+    eyre::ensure!(eki_.to_bytes() == bytes, "Round-trip changed the key!");
+
+    // mismatched matrix and IPFE key
+    let mut short = eki.clone();
+    short.s.pop();
+    eyre::ensure!(
+        short.validate().is_err(),
+        "Key with a missing matrix row should be rejected!"
+    );
+    eyre::ensure!(
+        ipmcfe::encrypt(&short, &vec![Scalar::one(); m], &Label::new()).is_err(),
+        "Encryption with an inconsistent key should fail!"
+    );
+    eyre::ensure!(
+        ipmcfe::PrivateKey::from_bytes(&short.to_bytes()).is_err(),
+        "Deserialization of an inconsistent key should fail!"
+    );
+    eyre::ensure!(
+        ipmcfe::PrivateKey::new(short.s, vec![ipfe::PrivateKey(Scalar::one()); m]).is_err(),
+        "Construction of an inconsistent key should fail!"
+    );
+    Ok(())
+}
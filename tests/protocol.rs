@@ -0,0 +1,106 @@
+use cosmian_bls12_381::{G1Projective, Scalar};
+use dmcfe::{
+    check_version, dsum, ipdmcfe, scheme_info,
+    tools::codec,
+    types::{self, DVec, Label, TMat},
+    PROTOCOL_VERSION,
+};
+use eyre::Result;
+use rand::rngs::ThreadRng;
+
+#[test]
+fn test_check_version() -> Result<()> {
+    check_version(PROTOCOL_VERSION)?;
+    for peer in [0, PROTOCOL_VERSION + 1, u32::MAX] {
+        eyre::ensure!(
+            check_version(peer).is_err(),
+            "Version {} should be rejected!",
+            peer
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_scheme_info() -> Result<()> {
+    let info = scheme_info();
+    eyre::ensure!(info.curve == "BLS12-381", "Wrong curve: {}!", info.curve);
+    eyre::ensure!(
+        info.dimension == types::DIM && info.dimension == DVec::new(0u8, 0u8).len(),
+        "Wrong dimension: {}!",
+        info.dimension
+    );
+    eyre::ensure!(
+        info.protocol_version == PROTOCOL_VERSION,
+        "Wrong protocol version: {}!",
+        info.protocol_version
+    );
+    Ok(())
+}
+
+#[test]
+fn test_format_version() -> Result<()> {
+    let mut rng = ThreadRng::default();
+    let dsum::KeyPair(dski, dpki) = dsum::client_setup(&mut rng);
+    let ski = ipdmcfe::setup(&dski, &[dpki], &mut rng)?;
+    let ci = ipdmcfe::encrypt(&Scalar::one(), &ski, &Label::from("Version"));
+    let u = DVec::new(*ci, G1Projective::generator());
+    let blobs = [
+        ci.to_bytes().to_vec(),
+        ski.t.to_bytes().to_vec(),
+        u.to_compressed(),
+    ];
+    for blob in &blobs {
+        eyre::ensure!(
+            blob[0] == codec::FORMAT_VERSION,
+            "Blobs should start with the format version!"
+        );
+    }
+    ipdmcfe::CypherText::from_bytes(&blobs[0])?;
+    TMat::<dsum::CypherText>::from_bytes(&blobs[1])?;
+    DVec::<G1Projective>::from_compressed(&blobs[2])?;
+
+    // bump the version tag
+    let blobs = blobs
+        .iter()
+        .map(|blob| {
+            let mut blob = blob.clone();
+            blob[0] = codec::FORMAT_VERSION + 1;
+            blob
+        })
+        .collect::<Vec<_>>();
+    eyre::ensure!(
+        ipdmcfe::CypherText::from_bytes(&blobs[0]).is_err()
+            && TMat::<dsum::CypherText>::from_bytes(&blobs[1]).is_err()
+            && DVec::<G1Projective>::from_compressed(&blobs[2]).is_err(),
+        "Unknown format versions should be rejected!"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_serialized_len() -> Result<()> {
+    let mut rng = ThreadRng::default();
+    let dsum::KeyPair(dski, dpki) = dsum::client_setup(&mut rng);
+    let ski = ipdmcfe::setup(&dski, &[dpki], &mut rng)?;
+    let ci = ipdmcfe::encrypt(&Scalar::one(), &ski, &Label::new());
+    eyre::ensure!(
+        ci.to_bytes().len() == ipdmcfe::CypherText::SERIALIZED_LEN,
+        "Wrong DMCFE cyphertext size!"
+    );
+    eyre::ensure!(
+        ipdmcfe::expected_ciphertext_bytes(3) == 3 * ipdmcfe::CypherText::SERIALIZED_LEN,
+        "Wrong size for several DMCFE cyphertexts!"
+    );
+    let di = dsum::encode(&Scalar::one(), &dski, &[dpki], &Label::new());
+    eyre::ensure!(
+        di.to_bytes().len() == dsum::CypherText::SERIALIZED_LEN,
+        "Wrong DSum cyphertext size!"
+    );
+    eyre::ensure!(
+        ski.t.to_bytes().len() == TMat::<dsum::CypherText>::SERIALIZED_LEN
+            && TMat::<dsum::CypherText>::SERIALIZED_LEN == 129,
+        "Wrong T matrix size!"
+    );
+    Ok(())
+}
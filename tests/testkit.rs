@@ -0,0 +1,68 @@
+use cosmian_bls12_381::Scalar;
+use dmcfe::{
+    dsum, ipdmcfe,
+    testkit::{self, RecordingRng, ReplayRng, SeededRng},
+};
+use eyre::Result;
+use rand::rngs::ThreadRng;
+use rand_core::{CryptoRng, RngCore};
+
+/// Generate the DMCFE secret keys of `n` clients, return their scalars.
+/// - `n`   : number of clients
+/// - `rng` : random number generator
+fn cohort_scalars<R: CryptoRng + RngCore>(n: usize, rng: &mut R) -> Result<Vec<Scalar>> {
+    let (dsk, dpk): (Vec<dsum::PrivateKey>, Vec<dsum::PublicKey>) = (0..n)
+        .map(|_| {
+            let dsum::KeyPair(dski, dpki) = dsum::client_setup(rng);
+            (dski, dpki)
+        })
+        .unzip();
+    let mut res = Vec::new();
+    for dski in &dsk {
+        let ski = ipdmcfe::setup(dski, &dpk, rng)?;
+        res.extend(ski.s.to_vec());
+        res.extend(ski.t.iter().flatten().map(|tij| **tij));
+    }
+    Ok(res)
+}
+
+#[test]
+fn test_record_replay() -> Result<()> {
+    let mut rng = RecordingRng::new(ThreadRng::default());
+    let keys = cohort_scalars(3, &mut rng)?;
+    let mut rng = ReplayRng::new(rng.into_record());
+    eyre::ensure!(
+        cohort_scalars(3, &mut rng)? == keys,
+        "Replayed keys are different!"
+    );
+    eyre::ensure!(rng.remaining() == 0, "All bytes should be replayed!");
+    Ok(())
+}
+
+#[test]
+fn test_ipfe_consistency() -> Result<()> {
+    let mut rng = ThreadRng::default();
+    for m in [1, 2, 10] {
+        testkit::check_ipfe_consistency(m, &mut rng)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_seeded_rng() -> Result<()> {
+    let draw = |seed, stream| {
+        let mut rng = SeededRng::new(seed, stream);
+        let mut bytes = [0; 100];
+        rng.fill_bytes(&mut bytes);
+        bytes
+    };
+    eyre::ensure!(
+        draw(1, 0) == draw(1, 0),
+        "The same seed and stream should give the same bytes!"
+    );
+    eyre::ensure!(
+        draw(1, 0) != draw(1, 1) && draw(1, 0) != draw(2, 0),
+        "Different seeds or streams should give different bytes!"
+    );
+    Ok(())
+}
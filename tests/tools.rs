@@ -0,0 +1,196 @@
+use cosmian_bls12_381::{pairing, G1Affine, G1Projective, G2Affine, G2Projective, Gt, Scalar};
+use dmcfe::tools;
+use eyre::Result;
+use rand::{rngs::ThreadRng, RngCore};
+
+#[test]
+fn test_scalar_u64_conversion() -> Result<()> {
+    for x in [0, 1, 2, 255, 256, 1 << 32, u64::MAX - 1, u64::MAX] {
+        let s = tools::scalar_from_u64_le(x);
+        eyre::ensure!(s == Scalar::from(x), "Wrong limb order for {}!", x);
+        eyre::ensure!(
+            tools::scalar_to_u64(&s)? == x,
+            "Round-trip failed for {}!",
+            x
+        );
+    }
+    eyre::ensure!(
+        tools::scalar_to_u64(&Scalar::from_raw([0, 1, 0, 0])).is_err(),
+        "Scalars greater than u64::MAX should be rejected!"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_scalar_u128_conversion() -> Result<()> {
+    for x in [
+        0,
+        1,
+        u64::MAX as u128,
+        u64::MAX as u128 + 1,
+        1 << 100,
+        (1 << 126) + 12345,
+    ] {
+        let s = tools::scalar_from_u128(x);
+        eyre::ensure!(
+            s == Scalar::from(x as u64)
+                + Scalar::from_raw([0, 1, 0, 0]) * Scalar::from((x >> 64) as u64),
+            "Wrong limb order for {}!",
+            x
+        );
+        eyre::ensure!(
+            tools::scalar_to_u128(&s)? == x,
+            "Round-trip failed for {}!",
+            x
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_gen_mul() -> Result<()> {
+    let mut rng = ThreadRng::default();
+    let mut a = vec![Scalar::zero(), Scalar::one(), -Scalar::one()];
+    a.extend((0..10).map(|_| {
+        let mut bytes = [0; 64];
+        rng.fill_bytes(&mut bytes);
+        Scalar::from_bytes_wide(&bytes)
+    }));
+    for ai in &a {
+        eyre::ensure!(
+            tools::gen_mul_g1(ai) == G1Projective::generator() * ai,
+            "Wrong multiplication in G1!"
+        );
+        eyre::ensure!(
+            tools::gen_mul_g2(ai) == G2Projective::generator() * ai,
+            "Wrong multiplication in G2!"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_transpose() -> Result<()> {
+    let m = vec![vec![1, 2, 3], vec![4, 5, 6]];
+    eyre::ensure!(
+        tools::transpose(&m)? == vec![vec![1, 4], vec![2, 5], vec![3, 6]],
+        "Wrong transposition!"
+    );
+    eyre::ensure!(
+        tools::transpose(&tools::transpose(&m)?)? == m,
+        "Transposing twice should give the original matrix!"
+    );
+    for ragged in [vec![vec![1, 2], vec![3]], vec![vec![1], vec![2, 3]]] {
+        eyre::ensure!(
+            tools::transpose(&ragged).is_err(),
+            "Ragged matrix {:?} should be rejected!",
+            ragged
+        );
+    }
+    eyre::ensure!(
+        tools::transpose::<u8>(&[]).is_err(),
+        "Empty matrix should be rejected!"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_scalar_dot() -> Result<()> {
+    let x: Vec<Scalar> = (1..=3).map(Scalar::from).collect();
+    let y: Vec<Scalar> = (4..=6).map(Scalar::from).collect();
+    eyre::ensure!(
+        tools::scalar_dot(&x, &y)? == Scalar::from(32),
+        "Wrong inner product!"
+    );
+    eyre::ensure!(
+        tools::scalar_dot_2d(&[x.clone(), y.clone()], &[y.clone(), x.clone()])? == Scalar::from(64),
+        "Wrong matrix inner product!"
+    );
+    eyre::ensure!(
+        tools::scalar_dot(&x, &y[1..]).is_err(),
+        "Length mismatch should be rejected!"
+    );
+    let (xy, ragged) = (vec![x.clone(), y.clone()], vec![x.clone(), y[1..].to_vec()]);
+    eyre::ensure!(
+        tools::scalar_dot_2d(&xy, &ragged).is_err() && tools::scalar_dot_2d(&xy[..1], &xy).is_err(),
+        "Shape mismatch should be rejected!"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_gt_pow() -> Result<()> {
+    let base = pairing(&G1Affine::generator(), &G2Affine::generator());
+    let mut acc = Gt::identity();
+    for x in 0..20 {
+        eyre::ensure!(
+            tools::gt_pow(&base, x) == acc,
+            "gt_pow differs from the repeated addition for {}",
+            x
+        );
+        acc += base;
+    }
+    for x in [1 << 20, u64::MAX] {
+        eyre::ensure!(
+            tools::gt_pow(&base, x) == base * Scalar::from(x),
+            "gt_pow differs from the scalar multiplication for {}",
+            x
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_mat_mul_shapes() -> Result<()> {
+    let x = vec![vec![Scalar::one(); 3]; 2];
+    let y = vec![G1Projective::generator(); 3];
+    let res = tools::mat_mul(&x, &y)?;
+    eyre::ensure!(
+        res == vec![G1Projective::generator() * Scalar::from(3); 2],
+        "Wrong matrix multiplication result!"
+    );
+
+    let err = tools::mat_mul(&x, &y[..2])
+        .err()
+        .ok_or_else(|| eyre::eyre!("Incompatible shapes should be rejected!"))?
+        .to_string();
+    eyre::ensure!(
+        err.contains("(2, 3)") && err.contains("(2, 1)"),
+        "The error should report both shapes: {}",
+        err
+    );
+    eyre::ensure!(
+        tools::mat_mul(&[], &y).is_err(),
+        "Empty matrices should be rejected!"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_aggregation_headroom() -> Result<()> {
+    eyre::ensure!(
+        tools::aggregation_headroom(0, 10, 100) == 10,
+        "Wrong headroom for an empty aggregation!"
+    );
+    eyre::ensure!(
+        tools::aggregation_headroom(9, 10, 100) == 1,
+        "One label should still fit!"
+    );
+    eyre::ensure!(
+        tools::aggregation_headroom(9, 10, 99) == 0,
+        "The next label may overflow the bound!"
+    );
+    eyre::ensure!(
+        tools::aggregation_headroom(10, 10, 100) == 0,
+        "The aggregation should be full!"
+    );
+    eyre::ensure!(
+        tools::aggregation_headroom(20, 10, 100) == 0,
+        "An overflowed aggregation has no headroom!"
+    );
+    eyre::ensure!(
+        tools::aggregation_headroom(1 << 40, 0, 100) == u64::MAX,
+        "A zero bound never exhausts the range!"
+    );
+    Ok(())
+}
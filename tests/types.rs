@@ -0,0 +1,166 @@
+use cosmian_bls12_381::{G1Affine, G1Projective, G2Projective, Scalar};
+use dmcfe::{
+    dsum,
+    tools::codec,
+    types::{enumerate_clients, BoundedScalarSum, ClientIndexed, DVec, TMat},
+};
+use eyre::Result;
+
+#[test]
+fn test_bounded_scalar_sum() -> Result<()> {
+    let mut sum = BoundedScalarSum::new(1_000);
+    for i in 1..=3 {
+        sum.add_product(&Scalar::from(i), 10, &Scalar::from(2 * i), 20)?;
+    }
+    eyre::ensure!(sum.sum() == Scalar::from(28), "Wrong sum!");
+    eyre::ensure!(sum.bound() == 600, "Wrong bound!");
+
+    // the next product may exceed the limit, even if its value does not
+    eyre::ensure!(
+        sum.add_product(&Scalar::one(), 10, &Scalar::one(), 50)
+            .is_err(),
+        "Exceeding the limit should be detected!"
+    );
+    // values greater than their bound are rejected
+    eyre::ensure!(
+        sum.add_product(&Scalar::from(11), 10, &Scalar::one(), 1)
+            .is_err(),
+        "Value exceeding its bound should be rejected!"
+    );
+    // values which do not even fit in a `u64` get the same error
+    let err = sum
+        .add_product(&Scalar::from_raw([0, 1, 0, 0]), 10, &Scalar::one(), 1)
+        .err()
+        .ok_or_else(|| eyre::eyre!("Value exceeding a u64 should be rejected!"))?;
+    eyre::ensure!(
+        err.to_string() == "Value exceeds its bound 10!",
+        "Wrong error: {}",
+        err
+    );
+    eyre::ensure!(
+        sum.sum() == Scalar::from(28) && sum.bound() == 600,
+        "Failed additions should not modify the sum!"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_enumerate_clients() -> Result<()> {
+    let values = vec![(30, 2), (10, 0), (20, 1)]
+        .into_iter()
+        .map(ClientIndexed::from)
+        .collect::<Vec<_>>();
+    let res = enumerate_clients(values)?
+        .map(|v| (v.id(), v.into_inner()))
+        .collect::<Vec<_>>();
+    eyre::ensure!(
+        res == vec![(0, 10), (1, 20), (2, 30)],
+        "Wrong order: {:?}",
+        res
+    );
+
+    // missing and duplicated IDs
+    for ids in [vec![0, 2], vec![0, 1, 1], vec![1, 2]] {
+        let values = ids
+            .iter()
+            .map(|&id| ClientIndexed::new(id, ()))
+            .collect::<Vec<_>>();
+        eyre::ensure!(
+            enumerate_clients(values).is_err(),
+            "Misaligned IDs {:?} should be rejected!",
+            ids
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_dvec_compression() -> Result<()> {
+    let u = DVec::new(
+        G1Projective::generator() * Scalar::from(3),
+        G1Projective::identity(),
+    );
+    let bytes = u.to_compressed();
+    eyre::ensure!(
+        DVec::<G1Projective>::from_compressed(&bytes)? == u,
+        "Wrong G1 round-trip!"
+    );
+    eyre::ensure!(
+        DVec::<G1Projective>::from_compressed(&bytes[..48]).is_err()
+            && DVec::<G1Projective>::from_compressed(&bytes[1..]).is_err(),
+        "Wrong sizes should be rejected!"
+    );
+
+    let d = DVec::new(
+        G2Projective::generator(),
+        G2Projective::generator() * Scalar::from(5),
+    );
+    let bytes = d.to_compressed();
+    eyre::ensure!(
+        DVec::<G2Projective>::from_compressed(&bytes)? == d,
+        "Wrong G2 round-trip!"
+    );
+    eyre::ensure!(
+        DVec::<G2Projective>::from_compressed(&[0; 192]).is_err(),
+        "Invalid encoding should be rejected!"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_tmat_serialization() -> Result<()> {
+    let scalars: Vec<Scalar> = (1..=4).map(Scalar::from).collect();
+    let t = scalars
+        .iter()
+        .map(|s| {
+            dsum::CypherText::from_bytes(&[&[codec::FORMAT_VERSION][..], &s.to_bytes()].concat())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let t = TMat::new(t[0], t[1], t[2], t[3]);
+    let bytes = t.to_bytes();
+
+    // row-major order
+    for (chunk, s) in bytes[1..].chunks_exact(32).zip(scalars.iter()) {
+        eyre::ensure!(chunk == s.to_bytes(), "Wrong serialization order!");
+    }
+    let t_ = TMat::<dsum::CypherText>::from_bytes(&bytes)?;
+    eyre::ensure!(t_.to_bytes() == bytes, "Wrong round-trip!");
+    eyre::ensure!(
+        *t_[0][1] == scalars[1] && *t_[1][0] == scalars[2],
+        "Entries are not at their positions!"
+    );
+    eyre::ensure!(
+        TMat::<dsum::CypherText>::from_bytes(&bytes[..96]).is_err()
+            && TMat::<dsum::CypherText>::from_bytes(
+                &[&[codec::FORMAT_VERSION][..], &[0xff; 128]].concat()
+            )
+            .is_err(),
+        "Invalid matrices should be rejected!"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_dvec_subgroup_check() -> Result<()> {
+    // look for a compressed point on the curve but outside the prime-order
+    // subgroup
+    let crafted = (2..=u8::MAX)
+        .map(|x| {
+            let mut point = [0; 48];
+            point[0] = 0x80;
+            point[47] = x;
+            [
+                &[codec::FORMAT_VERSION][..],
+                &G1Affine::generator().to_compressed(),
+                &point,
+            ]
+            .concat()
+        })
+        .find(|bytes| DVec::<G1Projective>::from_compressed_unchecked(bytes).is_ok())
+        .ok_or_else(|| eyre::eyre!("No point found on the curve!"))?;
+    eyre::ensure!(
+        DVec::<G1Projective>::from_compressed(&crafted).is_err(),
+        "Points outside the prime-order subgroup should be rejected!"
+    );
+    Ok(())
+}
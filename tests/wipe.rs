@@ -0,0 +1,94 @@
+//! Check that the secret-derived temporaries are wiped before being freed,
+//! using an allocator which inspects the freed buffers. This file holds a
+//! single test since the allocator is shared by the whole test binary.
+
+use cosmian_bls12_381::Scalar;
+use dmcfe::ipmcfe;
+use eyre::Result;
+use rand::rngs::ThreadRng;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+/// Size of the in-memory representation of a scalar
+const PATTERN_LEN: usize = std::mem::size_of::<Scalar>();
+
+/// In-memory representation of the secret-derived scalar to look for
+static PATTERN: [AtomicU8; PATTERN_LEN] = [const { AtomicU8::new(0) }; PATTERN_LEN];
+
+/// `true` while the freed buffers are inspected
+static SPYING: AtomicBool = AtomicBool::new(false);
+
+/// `true` if a freed buffer held the pattern
+static FOUND: AtomicBool = AtomicBool::new(false);
+
+/// System allocator recording whether a freed buffer holds the pattern
+struct SpyAllocator;
+
+unsafe impl GlobalAlloc for SpyAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if SPYING.load(Ordering::SeqCst) {
+            let found = layout.size() >= PATTERN_LEN
+                && (0..=layout.size() - PATTERN_LEN).any(|offset| {
+                    PATTERN.iter().enumerate().all(|(i, p)| {
+                        std::ptr::read_volatile(ptr.add(offset + i)) == p.load(Ordering::SeqCst)
+                    })
+                });
+            if found {
+                FOUND.store(true, Ordering::SeqCst);
+            }
+        }
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: SpyAllocator = SpyAllocator;
+
+/// Look for the given scalar in the buffers freed while running `f`.
+/// - `s`   : scalar to look for
+/// - `f`   : function to run
+fn is_freed_unwiped<T>(s: &Scalar, f: impl FnOnce() -> T) -> (bool, T) {
+    // SAFETY: a scalar is a plain array of limbs, without padding
+    let bytes = unsafe { std::slice::from_raw_parts(s as *const Scalar as *const u8, PATTERN_LEN) };
+    PATTERN
+        .iter()
+        .zip(bytes.iter())
+        .for_each(|(p, b)| p.store(*b, Ordering::SeqCst));
+    FOUND.store(false, Ordering::SeqCst);
+    SPYING.store(true, Ordering::SeqCst);
+    let res = f();
+    SPYING.store(false, Ordering::SeqCst);
+    (FOUND.load(Ordering::SeqCst), res)
+}
+
+#[test]
+fn test_dkey_gen_wipes_temporaries() -> Result<()> {
+    let mut rng = ThreadRng::default();
+    let m = 3;
+    let msk = vec![ipmcfe::setup(m, &mut rng)];
+    let y = vec![(1..=m as u64).map(Scalar::from).collect::<Vec<_>>()];
+
+    // first coefficient of the secret-derived term `dky_0 = S^T.y_0`
+    let dky = msk[0]
+        .s
+        .iter()
+        .zip(y[0].iter())
+        .map(|(sj, yj)| sj[0] * yj)
+        .sum::<Scalar>();
+
+    // the allocator detects the scalar in a freed buffer
+    let (found, _) = is_freed_unwiped(&dky, || drop(vec![dky; 2]));
+    eyre::ensure!(found, "The allocator should detect the scalar!");
+
+    let (found, dk) = is_freed_unwiped(&dky, || ipmcfe::dkey_gen(&msk, &y));
+    dk?;
+    eyre::ensure!(
+        !found,
+        "The secret-derived term was freed without being wiped!"
+    );
+    Ok(())
+}